@@ -0,0 +1,64 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tecs::{StorageKind, World};
+
+/// Keeps track of the bytes currently allocated.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+    System.alloc(layout)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    System.dealloc(ptr, layout)
+  }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct Position(u64, u64);
+
+struct Name(u64);
+
+const ENTITIES: u64 = 100_000;
+const NAMED: u64 = 10;
+
+/// Bytes taken by a world of positioned entities, the last `NAMED` of which
+/// also have a `Name` stored as `kind`.
+fn footprint(kind: Option<StorageKind>) -> usize {
+  let before = ALLOCATED.load(Ordering::Relaxed);
+  let mut world = World::new();
+  if let Some(kind) = kind {
+    world.set_storage::<Name>(kind);
+  }
+  let ids = world.spawn_batch((0..ENTITIES).map(|i| (Position(i, i),)));
+  if kind.is_some() {
+    for &id in &ids[ids.len() - NAMED as usize..] {
+      world.add_component(id, Name(id.index() as u64));
+    }
+  }
+  let bytes = ALLOCATED.load(Ordering::Relaxed) - before;
+  let named: Vec<u64> = world.entities().iter().filter_map(|&id| Some(world.get::<Name>(id)?.0)).collect();
+  assert_eq!(named.len() as u64, if kind.is_some() { NAMED } else { 0 });
+  assert!(named.iter().all(|&index| index >= ENTITIES - NAMED));
+  assert_eq!(world.get::<Position>(ids[0]).map(|pos| pos.0 + pos.1), Some(0));
+  bytes
+}
+
+fn main() {
+  let base = footprint(None);
+  let table = footprint(Some(StorageKind::Table));
+  let sparse = footprint(Some(StorageKind::SparseSet));
+
+  println!("{} entities with a Position, {} of them also named", ENTITIES, NAMED);
+  println!("positions only:        {} bytes", base);
+  println!("names in tables:       +{} bytes", table.saturating_sub(base));
+  println!("names in a sparse set: +{} bytes", sparse.saturating_sub(base));
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+
+use tecs::{query, query_mut, StorageKind, World};
+
+struct Position(i64);
+
+struct Name(i64);
+
+const ENTITIES: i64 = 100_000;
+const RUNS: u32 = 100;
+
+fn time(mut f: impl FnMut() -> i64) -> (i64, Duration) {
+  let start = Instant::now();
+  let mut total = 0;
+  for _ in 0..RUNS {
+    total += f();
+  }
+  (total, start.elapsed() / RUNS)
+}
+
+/// An entity the way a world without archetypes would keep it: every
+/// component type in an `Option`, plus a bitmask of the ones present.
+struct Row {
+  mask: u64,
+  position: Option<Position>,
+  name: Option<Name>,
+}
+
+const POSITION: u64 = 1 << 0;
+const NAME: u64 = 1 << 1;
+
+fn rows() -> Vec<Row> {
+  (0..ENTITIES)
+    .map(|i| {
+      let name = (i % 100 == 0).then_some(Name(i));
+      Row { mask: POSITION | if name.is_some() { NAME } else { 0 }, position: Some(Position(i)), name }
+    })
+    .collect()
+}
+
+fn populate(world: &mut World) {
+  for i in 0..ENTITIES {
+    let id = world.spawn();
+    world.add_component(id, Position(i));
+    if i % 100 == 0 {
+      world.add_component(id, Name(i));
+    }
+  }
+}
+
+fn main() {
+  let mut world = World::new();
+  populate(&mut world);
+
+  let (archetype, archetype_time) = time(|| {
+    query!(world, Position, Name).map(|(_, pos, name)| pos.0 + name.0).sum()
+  });
+  let (scan, scan_time) = time(|| {
+    world
+      .entities()
+      .iter()
+      .filter_map(|&id| Some(world.get::<Position>(id)?.0 + world.get::<Name>(id)?.0))
+      .sum()
+  });
+
+  let rows = rows();
+  let (options, options_time) = time(|| {
+    rows
+      .iter()
+      .filter_map(|row| Some(row.position.as_ref()?.0 + row.name.as_ref()?.0))
+      .sum()
+  });
+  let (mask, mask_time) = time(|| {
+    rows
+      .iter()
+      .filter(|row| row.mask & (POSITION | NAME) == POSITION | NAME)
+      .map(|row| row.position.as_ref().unwrap().0 + row.name.as_ref().unwrap().0)
+      .sum()
+  });
+
+  assert_eq!(archetype, scan);
+  assert_eq!(archetype, options);
+  assert_eq!(archetype, mask);
+  println!("{} entities, 1% with both Position and Name", ENTITIES);
+  println!("archetype query:   {:?}/iter", archetype_time);
+  println!("full scan:         {:?}/iter", scan_time);
+  println!("per-field Options: {:?}/iter", options_time);
+  println!("u64 mask scan:     {:?}/iter", mask_time);
+
+  let mut sparse_world = World::new();
+  sparse_world.set_storage::<Position>(StorageKind::SparseSet);
+  populate(&mut sparse_world);
+
+  let (sparse, sparse_time) = time(|| {
+    query_mut!(sparse_world, Position).map(|(_, pos)| { pos.0 += 1; pos.0 }).sum()
+  });
+  let (dense, dense_time) = time(|| {
+    query_mut!(world, Position).map(|(_, pos)| { pos.0 += 1; pos.0 }).sum()
+  });
+
+  assert_eq!(sparse, dense);
+  println!("mutating every Position");
+  println!("sparse-set storage: {:?}/iter", sparse_time);
+  println!("table storage:      {:?}/iter", dense_time);
+}
@@ -0,0 +1,38 @@
+use std::time::{Duration, Instant};
+
+use tecs::World;
+
+struct Particle(u64);
+
+const ENTITIES: u64 = 50_000;
+const RUNS: u32 = 20;
+
+fn time(mut f: impl FnMut() -> u64) -> (u64, Duration) {
+  let start = Instant::now();
+  let mut total = 0;
+  for _ in 0..RUNS {
+    total += f();
+  }
+  (total, start.elapsed() / RUNS)
+}
+
+fn main() {
+  let (batch, batch_time) = time(|| {
+    let mut world = World::new();
+    let ids = world.spawn_batch((0..ENTITIES).map(|i| (Particle(i),)));
+    world.get::<Particle>(ids[ids.len() - 1]).unwrap().0
+  });
+  let (single, single_time) = time(|| {
+    let mut world = World::new();
+    let mut last = None;
+    for i in 0..ENTITIES {
+      last = Some(world.spawn_bundle((Particle(i),)));
+    }
+    world.get::<Particle>(last.unwrap()).unwrap().0
+  });
+
+  assert_eq!(batch, single);
+  println!("spawning {} entities", ENTITIES);
+  println!("spawn_batch:       {:?}/iter", batch_time);
+  println!("spawn_bundle loop: {:?}/iter", single_time);
+}
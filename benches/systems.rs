@@ -0,0 +1,71 @@
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+use tecs::{Component, Query, ResMut, Schedule, World};
+
+struct Health(u64);
+
+struct Mana(u64);
+
+struct Speed(u64);
+
+struct Armor(u64);
+
+trait Stat: Component {
+  fn value(&self) -> u64;
+}
+
+macro_rules! impl_stat {
+  ($($name:ident),+) => {
+    $(impl Stat for $name {
+      fn value(&self) -> u64 {
+        self.0
+      }
+    })+
+  };
+}
+
+impl_stat!(Health, Mana, Speed, Armor);
+
+struct Total<T>(u64, PhantomData<fn() -> T>);
+
+const ENTITIES: u64 = 100_000;
+const RUNS: u32 = 20;
+
+/// Reads one stat of every entity, so the four of them never conflict.
+fn total_system<T: Stat>(mut query: Query<&T>, mut total: ResMut<Total<T>>) {
+  total.0 += query.iter().map(|(_, stat)| stat.value()).sum::<u64>();
+}
+
+fn main() {
+  let mut world = World::new();
+  world.spawn_batch((0..ENTITIES).map(|i| (Health(i), Mana(i), Speed(i))));
+  for id in world.entities().to_vec() {
+    world.add_component(id, Armor(id.index() as u64));
+  }
+  world.insert_resource(Total::<Health>(0, PhantomData));
+  world.insert_resource(Total::<Mana>(0, PhantomData));
+  world.insert_resource(Total::<Speed>(0, PhantomData));
+  world.insert_resource(Total::<Armor>(0, PhantomData));
+
+  let mut schedule = Schedule::new();
+  #[cfg(feature = "parallel")]
+  schedule.set_parallelism(tecs::ParallelismMode::Rayon);
+  schedule.add_system(total_system::<Health>);
+  schedule.add_system(total_system::<Mana>);
+  schedule.add_system(total_system::<Speed>);
+  schedule.add_system(total_system::<Armor>);
+
+  let start = Instant::now();
+  for _ in 0..RUNS {
+    schedule.run(&mut world);
+  }
+  let elapsed: Duration = start.elapsed() / RUNS;
+
+  let expected = RUNS as u64 * ENTITIES * (ENTITIES - 1) / 2;
+  assert_eq!(world.resource::<Total<Health>>().0, expected);
+  assert_eq!(world.resource::<Total<Armor>>().0, expected);
+  let mode = if cfg!(feature = "parallel") { "parallel" } else { "sequential" };
+  println!("4 read-only systems over {} entities", ENTITIES);
+  println!("{}: {:?}/run", mode, elapsed);
+}
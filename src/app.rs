@@ -0,0 +1,104 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{IntoSystem, Plugin, Resource, Schedule, World};
+
+/// Insert it as a resource, e.g. with `Commands::insert_resource`, to make
+/// `App::run` return after the current frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AppExit;
+
+/// Owns a world and its schedule and drives them in a game loop.
+pub struct App {
+  pub world: World,
+  pub schedule: Schedule,
+  frame: Duration,
+}
+
+impl Default for App {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl App {
+  /// An empty app running at 60 frames per second.
+  pub fn new() -> Self {
+    Self {
+      world: World::new(),
+      schedule: Schedule::new(),
+      frame: Duration::from_secs(1) / 60,
+    }
+  }
+
+  pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+    self.world.add_plugin(&mut self.schedule, plugin);
+    self
+  }
+
+  pub fn add_system<M, S>(&mut self, system: S) -> &mut Self
+  where
+    S: IntoSystem<M>,
+    S::System: 'static,
+  {
+    self.schedule.add_system(system);
+    self
+  }
+
+  pub fn insert_resource<R: Resource>(&mut self, resource: R) -> &mut Self {
+    self.world.insert_resource(resource);
+    self
+  }
+
+  /// Caps the loop of `run` at `fps` frames per second. Frames that take
+  /// longer than that are not made up for.
+  pub fn set_frame_rate(&mut self, fps: u32) -> &mut Self {
+    assert!(fps > 0, "frame rate must be positive");
+    self.frame = Duration::from_secs(1) / fps;
+    self
+  }
+
+  /// Runs the schedule once.
+  pub fn update(&mut self) {
+    self.schedule.run(&mut self.world);
+  }
+
+  /// Runs frames at the target frame rate until a system inserts `AppExit`,
+  /// which is removed again before returning.
+  pub fn run(&mut self) {
+    loop {
+      let start = Instant::now();
+      self.update();
+      if self.world.take_resource::<AppExit>().is_some() {
+        return;
+      }
+      if let Some(rest) = self.frame.checked_sub(start.elapsed()) {
+        thread::sleep(rest);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Commands, DefaultPlugins, FrameCount, Res};
+
+  fn quit_on_third_frame(frame: Res<FrameCount>, commands: &mut Commands) {
+    if frame.0 == 3 {
+      commands.insert_resource(AppExit);
+    }
+  }
+
+  #[test]
+  fn run_returns_once_a_system_exits() {
+    let mut app = App::new();
+    app.add_plugin(DefaultPlugins).add_system(quit_on_third_frame).set_frame_rate(1000);
+    app.run();
+
+    assert_eq!(app.world.resource::<FrameCount>(), &FrameCount(3));
+    assert!(app.world.get_resource::<AppExit>().is_none());
+    app.update();
+    assert_eq!(app.world.resource::<FrameCount>(), &FrameCount(4));
+  }
+}
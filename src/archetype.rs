@@ -0,0 +1,378 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+
+use crate::storage::Column;
+#[cfg(not(feature = "std"))]
+use crate::GetDisjointMut;
+use crate::{EntityId, HashMap, HashSet, StorageMut};
+
+/// The entities that share exactly the same set of table components, along
+/// with a column for each of those types. Sparse-set components are not part
+/// of any archetype.
+pub struct Archetype {
+  types: BTreeSet<TypeId>,
+  entities: Vec<EntityId>,
+  columns: HashMap<TypeId, Box<dyn Column>>,
+}
+
+impl Archetype {
+  pub fn types(&self) -> impl Iterator<Item = TypeId> + '_ {
+    self.types.iter().copied()
+  }
+
+  pub fn entities(&self) -> &[EntityId] {
+    &self.entities
+  }
+
+  pub fn contains_all(&self, types: &[TypeId]) -> bool {
+    types.iter().all(|ty| self.types.contains(ty))
+  }
+}
+
+#[derive(Clone, Copy)]
+struct Location {
+  archetype: usize,
+  row: usize,
+}
+
+pub(crate) struct Archetypes {
+  archetypes: Vec<Archetype>,
+  index: HashMap<BTreeSet<TypeId>, usize>,
+  locations: Vec<Location>,
+  tables: HashMap<TypeId, fn() -> Box<dyn Column>>,
+  sparse: HashSet<TypeId>,
+  names: HashMap<TypeId, &'static str>,
+}
+
+impl Archetypes {
+  pub(crate) fn new() -> Self {
+    let mut archetypes = Self {
+      archetypes: vec![],
+      index: HashMap::new(),
+      locations: vec![],
+      tables: HashMap::new(),
+      sparse: HashSet::new(),
+      names: HashMap::new(),
+    };
+    archetypes.archetype_for(BTreeSet::new());
+    archetypes
+  }
+
+  /// Makes room for `additional` more entities without components.
+  pub(crate) fn reserve(&mut self, additional: usize) {
+    self.locations.reserve(additional);
+    self.archetypes[0].entities.reserve(additional);
+  }
+
+  pub(crate) fn as_slice(&self) -> &[Archetype] {
+    &self.archetypes
+  }
+
+  /// Entities of every archetype that has all of `types`, grouped by
+  /// archetype.
+  pub(crate) fn matching<'a>(&'a self, types: &'a [TypeId]) -> impl Iterator<Item = EntityId> + 'a {
+    self.archetypes
+      .iter()
+      .filter(move |archetype| archetype.contains_all(types))
+      .flat_map(|archetype| archetype.entities.iter().copied())
+  }
+
+  /// Whether any entity currently has a component of type `ty`.
+  pub(crate) fn is_used(&self, ty: TypeId) -> bool {
+    self.archetypes
+      .iter()
+      .any(|archetype| archetype.types.contains(&ty) && !archetype.entities.is_empty())
+  }
+
+  /// Stores `ty`, called `name`, in columns created by `new_column`, or
+  /// leaves it out of the archetypes when it is `None`. No entity may have
+  /// `ty` yet.
+  pub(crate) fn set_table(
+    &mut self,
+    ty: TypeId,
+    name: &'static str,
+    new_column: Option<fn() -> Box<dyn Column>>,
+  ) {
+    self.names.insert(ty, name);
+    for archetype in &mut self.archetypes {
+      if archetype.types.contains(&ty) {
+        match new_column {
+          Some(new_column) => archetype.columns.insert(ty, new_column()),
+          None => archetype.columns.remove(&ty),
+        };
+      }
+    }
+    match new_column {
+      Some(new_column) => {
+        self.tables.insert(ty, new_column);
+        self.sparse.remove(&ty);
+      }
+      None => {
+        self.tables.remove(&ty);
+        self.sparse.insert(ty);
+      }
+    }
+  }
+
+  /// Every component type whose storage has been chosen, with its name.
+  pub(crate) fn names(&self) -> impl Iterator<Item = (TypeId, &'static str)> + '_ {
+    self.names.iter().map(|(ty, name)| (*ty, *name))
+  }
+
+  pub(crate) fn name(&self, ty: TypeId) -> &'static str {
+    self.names.get(&ty).copied().unwrap_or("<unknown>")
+  }
+
+  /// The constructor of `ty`'s columns, if it uses table storage.
+  pub(crate) fn table(&self, ty: TypeId) -> Option<fn() -> Box<dyn Column>> {
+    self.tables.get(&ty).copied()
+  }
+
+  pub(crate) fn is_table(&self, ty: TypeId) -> bool {
+    self.tables.contains_key(&ty)
+  }
+
+  pub(crate) fn is_sparse(&self, ty: TypeId) -> bool {
+    self.sparse.contains(&ty)
+  }
+
+  pub(crate) fn insert(&mut self, id: EntityId) {
+    self.insert_into(id, 0);
+  }
+
+  /// Adds `id` to `archetype`, whose columns the caller has to push a value
+  /// to each.
+  pub(crate) fn insert_into(&mut self, id: EntityId, archetype: usize) {
+    let index = id.index as usize;
+    if index >= self.locations.len() {
+      self.locations.resize(index + 1, Location { archetype: 0, row: 0 });
+    }
+    self.push(id, archetype);
+  }
+
+  /// The columns of `types` in `archetype`, which has to have all of them.
+  pub(crate) fn columns_mut<const N: usize>(
+    &mut self,
+    archetype: usize,
+    types: [&TypeId; N],
+  ) -> [&mut Box<dyn Column>; N] {
+    self.archetypes[archetype]
+      .columns
+      .get_disjoint_mut(types)
+      .map(|column| column.expect("archetypes have a column for every table type"))
+  }
+
+  /// Removes `id` along with all of its table components.
+  pub(crate) fn remove(&mut self, id: EntityId) {
+    let Location { archetype, row } = self.locations[id.index as usize];
+    for column in self.archetypes[archetype].columns.values_mut() {
+      column.swap_remove(row);
+    }
+    self.detach(id);
+  }
+
+  /// Removes every entity, keeping the archetypes and their columns' capacity.
+  pub(crate) fn clear(&mut self) {
+    for archetype in &mut self.archetypes {
+      archetype.entities.clear();
+      for column in archetype.columns.values_mut() {
+        column.clear();
+      }
+    }
+  }
+
+  pub(crate) fn get(&self, id: EntityId, ty: TypeId) -> Option<&dyn Any> {
+    let Location { archetype, row } = self.locations[id.index as usize];
+    Some(self.archetypes[archetype].columns.get(&ty)?.get(row))
+  }
+
+  pub(crate) fn get_mut(&mut self, id: EntityId, ty: TypeId) -> Option<&mut dyn Any> {
+    let Location { archetype, row } = self.locations[id.index as usize];
+    Some(self.archetypes[archetype].columns.get_mut(&ty)?.get_mut(row))
+  }
+
+  /// Overwrites the table component `ty` that `id` already has, returning
+  /// the previous value.
+  pub(crate) fn replace(
+    &mut self,
+    id: EntityId,
+    ty: TypeId,
+    value: Box<dyn Any>,
+  ) -> Option<Box<dyn Any>> {
+    let Location { archetype, row } = self.locations[id.index as usize];
+    let column = self.archetypes[archetype].columns.get_mut(&ty)?;
+    Some(column.replace(row, value))
+  }
+
+  /// Moves `id` to the archetype that also has the table component `ty`.
+  pub(crate) fn add_type(&mut self, id: EntityId, ty: TypeId, value: Box<dyn Any>) {
+    let mut types = self.types_of(id).clone();
+    if !types.insert(ty) {
+      return;
+    }
+    self.move_to(id, types);
+    let Location { archetype, .. } = self.locations[id.index as usize];
+    self.archetypes[archetype]
+      .columns
+      .get_mut(&ty)
+      .expect("archetypes have a column for every table type")
+      .push(value);
+  }
+
+  /// Moves `id` to the archetype without `ty`, returning the removed value.
+  pub(crate) fn remove_type(&mut self, id: EntityId, ty: TypeId) -> Option<Box<dyn Any>> {
+    let mut types = self.types_of(id).clone();
+    if !types.remove(&ty) {
+      return None;
+    }
+    self.move_to(id, types)
+  }
+
+  /// The index in `as_slice` of the archetype `id` is in.
+  pub(crate) fn archetype_of(&self, id: EntityId) -> usize {
+    self.locations[id.index as usize].archetype
+  }
+
+  pub(crate) fn types_of(&self, id: EntityId) -> &BTreeSet<TypeId> {
+    &self.archetypes[self.locations[id.index as usize].archetype].types
+  }
+
+  /// Takes every column of `ty` out of the archetypes, e.g. for the duration of
+  /// a system that queries it mutably.
+  pub(crate) fn take_columns(&mut self, ty: TypeId) -> Vec<(usize, Box<dyn Column>)> {
+    self.archetypes
+      .iter_mut()
+      .enumerate()
+      .filter_map(|(index, archetype)| Some((index, archetype.columns.remove(&ty)?)))
+      .collect()
+  }
+
+  pub(crate) fn restore_columns(&mut self, ty: TypeId, columns: Vec<(usize, Box<dyn Column>)>) {
+    for (archetype, column) in columns {
+      self.archetypes[archetype].columns.insert(ty, column);
+    }
+  }
+
+  /// Adds the table components of `types` to `storages`, for the entities of
+  /// every archetype that has all of the `required` types.
+  pub(crate) fn extend_storages<'a, const N: usize>(
+    &'a mut self,
+    required: &[TypeId],
+    types: [&TypeId; N],
+    storages: &mut [StorageMut<'a>; N],
+  ) {
+    for archetype in &mut self.archetypes {
+      if !archetype.contains_all(required) {
+        continue;
+      }
+      let Archetype { entities, columns, .. } = archetype;
+      for (storage, column) in storages.iter_mut().zip(columns.get_disjoint_mut(types)) {
+        if let Some(column) = column {
+          storage.extend(entities.iter().copied().zip(column.iter_mut()));
+        }
+      }
+    }
+  }
+
+  /// Moves `id` and its components to the archetype of `types`. At most one
+  /// component can be left behind, which is returned.
+  fn move_to(&mut self, id: EntityId, types: BTreeSet<TypeId>) -> Option<Box<dyn Any>> {
+    let Location { archetype: from, row } = self.locations[id.index as usize];
+    let to = self.archetype_for(types);
+    let [from_archetype, to_archetype] = self.archetypes
+      .get_disjoint_mut([from, to])
+      .expect("an entity moves to a different archetype");
+    let mut removed = None;
+    for (ty, column) in &mut from_archetype.columns {
+      match to_archetype.columns.get_mut(ty) {
+        Some(other) => column.move_row(row, other.as_mut()),
+        None => removed = Some(column.swap_remove(row)),
+      }
+    }
+    self.detach(id);
+    self.push(id, to);
+    removed
+  }
+
+  /// Removes `id` from its archetype's entity list, keeping the rows of the
+  /// other entities in sync with their columns.
+  fn detach(&mut self, id: EntityId) {
+    let Location { archetype, row } = self.locations[id.index as usize];
+    let entities = &mut self.archetypes[archetype].entities;
+    entities.swap_remove(row);
+    if let Some(moved) = entities.get(row) {
+      self.locations[moved.index as usize].row = row;
+    }
+  }
+
+  fn push(&mut self, id: EntityId, archetype: usize) {
+    let entities = &mut self.archetypes[archetype].entities;
+    self.locations[id.index as usize] = Location { archetype, row: entities.len() };
+    entities.push(id);
+  }
+
+  pub(crate) fn archetype_for(&mut self, types: BTreeSet<TypeId>) -> usize {
+    if let Some(&archetype) = self.index.get(&types) {
+      return archetype;
+    }
+    let archetype = self.archetypes.len();
+    let columns = types
+      .iter()
+      .filter_map(|ty| Some((*ty, self.tables.get(ty)?())))
+      .collect();
+    self.index.insert(types.clone(), archetype);
+    self.archetypes.push(Archetype { types, entities: vec![], columns });
+    archetype
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::any::TypeId;
+
+  use crate::{query, EntityId, World};
+
+  #[derive(Debug, PartialEq)]
+  struct Position(i32, i32);
+
+  #[derive(Debug, PartialEq)]
+  struct Name(&'static str);
+
+  #[test]
+  fn entities_are_grouped_by_their_component_types() {
+    let mut world = World::new();
+    world.spawn_bundle((Position(0, 0),));
+    world.spawn_bundle((Position(1, 1),));
+    let named = world.spawn_bundle((Position(2, 2), Name("named")));
+
+    let both = [TypeId::of::<Position>(), TypeId::of::<Name>()];
+    let matching: Vec<&[EntityId]> = world
+      .archetypes()
+      .iter()
+      .filter(|archetype| archetype.contains_all(&both))
+      .map(|archetype| archetype.entities())
+      .collect();
+    assert_eq!(matching, [&[named][..]]);
+    let ids: Vec<EntityId> = query!(world, Position, Name).map(|(id, ..)| id).collect();
+    assert_eq!(ids, vec![named]);
+  }
+
+  #[test]
+  fn components_move_with_their_entity_between_archetypes() {
+    let mut world = World::new();
+    let a = world.spawn_bundle((Position(0, 0), Name("a")));
+    let b = world.spawn_bundle((Position(1, 1), Name("b")));
+
+    world.remove_component::<Name>(a);
+    assert_eq!(world.get::<Position>(a).map(|pos| pos.0), Some(0));
+    assert_eq!(world.get::<Name>(b).map(|name| name.0), Some("b"));
+    assert_eq!(world.get::<Position>(b).map(|pos| pos.1), Some(1));
+
+    world.add_component(a, Name("again"));
+    assert_eq!(world.get::<Name>(a).map(|name| name.0), Some("again"));
+    assert_eq!(world.get::<Position>(a).map(|pos| pos.0), Some(0));
+  }
+}
@@ -0,0 +1,265 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use core::any::{Any, TypeId};
+use core::error::Error;
+use core::fmt;
+
+use crate::archetype::Archetypes;
+use crate::{AnyResource, Children, Component, EntityId, HashMap, Name, ObserverQueue, Parent, World};
+
+pub(crate) type CloneFn = fn(&dyn Any) -> Box<AnyResource>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloneError {
+  /// The entity has a component of the named type that was not registered
+  /// with `World::register_clone`.
+  NotCloneable(&'static str),
+  NoSuchEntity(EntityId),
+  /// The world has a resource of the named type that was not registered
+  /// with `World::register_clone`.
+  NotCloneableResource(&'static str),
+}
+
+impl fmt::Display for CloneError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CloneError::NotCloneable(name) => write!(f, "component `{}` is not cloneable", name),
+      CloneError::NoSuchEntity(id) => write!(f, "entity {} does not exist", id),
+      CloneError::NotCloneableResource(name) => write!(f, "resource `{}` is not cloneable", name),
+    }
+  }
+}
+
+impl Error for CloneError {}
+
+fn clone_boxed<T: Component + Clone>(component: &dyn Any) -> Box<AnyResource> {
+  let component = component.downcast_ref::<T>().expect("cloner registered for its own type");
+  Box::new(component.clone())
+}
+
+impl World {
+  /// Lets `clone_entity` and `try_clone` duplicate components, and
+  /// `try_clone` resources, of type `T`.
+  pub fn register_clone<T: Component + Clone>(&mut self) {
+    self.cloners.insert(TypeId::of::<T>(), clone_boxed::<T>);
+  }
+
+  /// The cloner of `ty`, which the world's own components need no
+  /// registration for.
+  fn cloner(&self, ty: TypeId) -> Option<CloneFn> {
+    if let Some(&clone) = self.cloners.get(&ty) {
+      Some(clone)
+    } else if ty == TypeId::of::<Parent>() {
+      Some(clone_boxed::<Parent>)
+    } else if ty == TypeId::of::<Children>() {
+      Some(clone_boxed::<Children>)
+    } else if ty == TypeId::of::<Name>() {
+      Some(clone_boxed::<Name>)
+    } else {
+      None
+    }
+  }
+
+  /// Spawns a new entity with a clone of every component of `id`. Nothing is
+  /// spawned unless all of them were registered with `register_clone`. The
+  /// clones count as freshly added rather than copying the change tracking
+  /// of the originals. The clone shares the original's `Parent` but none of
+  /// its `Children`.
+  pub fn clone_entity(&mut self, id: EntityId) -> Result<EntityId, CloneError> {
+    if !self.is_alive(id) {
+      return Err(CloneError::NoSuchEntity(id));
+    }
+    let mut components = vec![];
+    for ty in self.component_types(id) {
+      if ty == TypeId::of::<Children>() {
+        continue;
+      }
+      let clone = self.cloner(ty).ok_or_else(|| CloneError::NotCloneable(self.archetypes.name(ty)))?;
+      let component = self.get_dyn(id, ty).expect("entity has each of its component types");
+      components.push((ty, clone(component)));
+    }
+    let clone = self.spawn();
+    self.deferring_observers(|world| {
+      for (ty, component) in components {
+        world.insert_boxed(clone, ty, component);
+      }
+    });
+    Ok(clone)
+  }
+
+  /// A copy of the world, e.g. to simulate several moves ahead on forks of
+  /// it. Entities keep their ids, their order and their change tracking,
+  /// and hooks, observers and registrations carry over. Components and
+  /// resources are independent copies, but hooks and observers are shared
+  /// `Rc` (`Arc` under `parallel`) handles, so a closure that captures state
+  /// sees both worlds. No hooks or observers run while copying.
+  ///
+  /// Fails unless every component and resource type in the world, other
+  /// than `Parent`, `Children` and `Name`, was registered with
+  /// `register_clone`.
+  pub fn try_clone(&self) -> Result<World, CloneError> {
+    let mut resources = HashMap::new();
+    for (&ty, resource) in &self.resources {
+      let clone = self.cloners
+        .get(&ty)
+        .ok_or_else(|| CloneError::NotCloneableResource(self.resource_names[&ty]))?;
+      resources.insert(ty, clone(&**resource));
+    }
+    let mut components = vec![];
+    for &id in &self.entities {
+      for ty in self.component_types(id) {
+        let clone = self.cloner(ty).ok_or_else(|| CloneError::NotCloneable(self.archetypes.name(ty)))?;
+        let component = self.get_dyn(id, ty).expect("entity has each of its component types");
+        components.push((id, ty, clone(component)));
+      }
+    }
+
+    let mut archetypes = Archetypes::new();
+    for (ty, name) in self.archetypes.names() {
+      archetypes.set_table(ty, name, self.archetypes.table(ty));
+    }
+    for &id in &self.entities {
+      archetypes.insert(id);
+    }
+    let mut sets: HashMap<_, _> = self.components.iter().map(|(&ty, set)| (ty, set.empty())).collect();
+    for (id, ty, component) in components {
+      match sets.get_mut(&ty) {
+        Some(set) => {
+          set.insert(id, component);
+        }
+        None => archetypes.add_type(id, ty, component),
+      }
+    }
+    Ok(World {
+      entities: self.entities.clone(),
+      generations: self.generations.clone(),
+      free: self.free.clone(),
+      spawn_order: self.spawn_order.clone(),
+      spawned: self.spawned,
+      reserved: self.reserved.clone(),
+      components: sets,
+      resources,
+      resource_names: self.resource_names.clone(),
+      changes: self.changes.clone(),
+      tick: self.tick,
+      added: self.added.clone(),
+      archetypes,
+      // Shared handles, not deep copies: both worlds call the same closures.
+      on_add: self.on_add.clone(),
+      on_remove: self.on_remove.clone(),
+      cloners: self.cloners.clone(),
+      debuggers: self.debuggers.clone(),
+      observers: self.observers.clone(),
+      next_observer: self.next_observer,
+      observed: ObserverQueue::default(),
+      observing: 0,
+      cascades: self.cascades.clone(),
+      names: self.names.clone(),
+      tags: self.tags.clone(),
+      plugins: self.plugins.clone(),
+      entity_limit: self.entity_limit,
+      previous: self.previous
+        .iter()
+        .map(|(ty, previous)| (*ty, (self.snapshots[ty].clone)(&**previous)))
+        .collect(),
+      snapshots: self.snapshots.clone(),
+      #[cfg(feature = "serde")]
+      registered: self.registered.clone(),
+      #[cfg(feature = "serde")]
+      registrations: self.registrations.clone(),
+      #[cfg(feature = "serde")]
+      migrations: self.migrations.clone(),
+    })
+  }
+}
+
+/// Panics where `World::try_clone` would fail, i.e. on any component or
+/// resource type not registered with `World::register_clone`. Hooks and
+/// observers are shared with the copy, as with `try_clone`.
+impl Clone for World {
+  fn clone(&self) -> Self {
+    self.try_clone().unwrap_or_else(|error| panic!("{}", error))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::CloneError;
+  use crate::{query, EntityId, Name, World};
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Position(i32, i32);
+
+  #[derive(Debug, PartialEq)]
+  struct Handle(u32);
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Score(u32);
+
+  #[test]
+  fn cloned_entities_get_a_copy_of_each_component() {
+    let mut world = World::new();
+    world.register_clone::<Position>();
+    let id = world.spawn_bundle((Position(1, 2), Name::new("original")));
+    let clone = world.clone_entity(id).unwrap();
+
+    assert_ne!(clone, id);
+    assert_eq!(world.get::<Position>(clone), Some(&Position(1, 2)));
+    assert_eq!(world.get::<Name>(clone).map(Name::as_str), Some("original"));
+    world.despawn(id);
+    assert_eq!(world.clone_entity(id), Err(CloneError::NoSuchEntity(id)));
+  }
+
+  #[test]
+  fn cloned_worlds_are_independent() {
+    let mut world = World::new();
+    world.register_clone::<Position>();
+    world.register_clone::<Score>();
+    world.insert_resource(Score(3));
+    let id = world.spawn_bundle((Position(0, 0),));
+    let mut fork = world.try_clone().unwrap();
+    fork.get_mut::<Position>(id).unwrap().0 = 5;
+    fork.get_resource_mut::<Score>().unwrap().0 = 4;
+    fork.spawn_bundle((Position(9, 9),));
+
+    let positions: Vec<(EntityId, Position)> = query!(world, Position).map(|(id, pos)| (id, pos.clone())).collect();
+    assert_eq!(positions, [(id, Position(0, 0))]);
+    assert_eq!(world.get_resource::<Score>(), Some(&Score(3)));
+    assert_eq!(fork.get::<Position>(id), Some(&Position(5, 0)));
+    assert_eq!(query!(fork, Position).count(), 2);
+  }
+
+  #[test]
+  fn unregistered_types_are_named_in_errors() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Handle(0),));
+    let error = world.clone_entity(id).unwrap_err();
+    assert_eq!(error, CloneError::NotCloneable(core::any::type_name::<Handle>()));
+    assert!(error.to_string().ends_with("Handle` is not cloneable"));
+
+    world.despawn(id);
+    world.insert_resource(Handle(1));
+    let error = world.try_clone().err().unwrap();
+    assert_eq!(error, CloneError::NotCloneableResource(core::any::type_name::<Handle>()));
+  }
+
+  #[test]
+  fn clone_copies_registered_worlds() {
+    let mut world = World::new();
+    world.register_clone::<Position>();
+    let id = world.spawn_bundle((Position(1, 1), Name::new("original")));
+    let mut fork = world.clone();
+    fork.get_mut::<Position>(id).unwrap().1 = 7;
+
+    assert_eq!(world.get::<Position>(id), Some(&Position(1, 1)));
+    assert_eq!(fork.get::<Position>(id), Some(&Position(1, 7)));
+  }
+
+  #[test]
+  #[should_panic(expected = "is not cloneable")]
+  fn clone_panics_on_unregistered_components() {
+    let mut world = World::new();
+    world.spawn_bundle((Handle(0),));
+    let _ = world.clone();
+  }
+}
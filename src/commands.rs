@@ -0,0 +1,68 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::TypeId;
+
+use crate::{Bundle, Component, EntityId, MaybeSend, Resource, World};
+
+#[cfg(not(feature = "parallel"))]
+pub type InsertFn = Box<dyn FnOnce(&mut World, EntityId)>;
+#[cfg(feature = "parallel")]
+pub type InsertFn = Box<dyn FnOnce(&mut World, EntityId) + Send>;
+
+#[cfg(not(feature = "parallel"))]
+pub type UpdateFn = Box<dyn FnOnce(&mut World)>;
+#[cfg(feature = "parallel")]
+pub type UpdateFn = Box<dyn FnOnce(&mut World) + Send>;
+
+pub enum Command {
+  Spawn(InsertFn),
+  Despawn(EntityId),
+  AddComponent(EntityId, InsertFn),
+  RemoveComponent(EntityId, TypeId),
+  Update(UpdateFn),
+}
+
+#[derive(Default)]
+pub struct Commands {
+  pub(crate) queue: Vec<Command>,
+}
+
+impl Commands {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn push(&mut self, command: Command) {
+    self.queue.push(command);
+  }
+
+  pub fn spawn(&mut self, bundle: impl Bundle + MaybeSend + 'static) {
+    self.push(Command::Spawn(Box::new(move |world, id| {
+      bundle.insert_into(world, id)
+    })));
+  }
+
+  pub fn despawn(&mut self, id: EntityId) {
+    self.push(Command::Despawn(id));
+  }
+
+  pub fn add_component<T: Component>(&mut self, id: EntityId, component: T) {
+    self.push(Command::AddComponent(id, Box::new(move |world, id| {
+      world.add_component(id, component)
+    })));
+  }
+
+  pub fn remove_component<T: Component>(&mut self, id: EntityId) {
+    self.push(Command::RemoveComponent(id, TypeId::of::<T>()));
+  }
+
+  pub fn update<T: Component>(&mut self, id: EntityId, update: impl FnOnce(&mut T) + MaybeSend + 'static) {
+    self.push(Command::Update(Box::new(move |world| {
+      world.update(id, update);
+    })));
+  }
+
+  pub fn insert_resource<R: Resource + MaybeSend>(&mut self, resource: R) {
+    self.push(Command::Update(Box::new(move |world| world.insert_resource(resource))));
+  }
+}
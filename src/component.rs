@@ -0,0 +1,125 @@
+use alloc::boxed::Box;
+use core::any::{type_name, TypeId};
+use core::error::Error;
+use core::fmt;
+
+use crate::{Component, EntityId, World};
+
+/// Why `World::get_component` found no component, or `insert_component`
+/// had no entity to insert into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComponentError {
+  NoSuchEntity(EntityId),
+  /// The entity is alive but has no component of the named type.
+  MissingComponent(EntityId, &'static str),
+}
+
+impl fmt::Display for ComponentError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ComponentError::NoSuchEntity(id) => write!(f, "entity {} does not exist", id),
+      ComponentError::MissingComponent(id, name) => {
+        write!(f, "entity {} has no `{}`", id, name)
+      }
+    }
+  }
+}
+
+impl Error for ComponentError {}
+
+impl World {
+  /// Whether `id` is alive and has a `T`.
+  pub fn has_component<T: Component>(&self, id: EntityId) -> bool {
+    self.has(id, TypeId::of::<T>())
+  }
+
+  /// Like `get`, but tells a despawned entity apart from a missing component.
+  pub fn get_component<T: Component>(&self, id: EntityId) -> Result<&T, ComponentError> {
+    if !self.is_alive(id) {
+      return Err(ComponentError::NoSuchEntity(id));
+    }
+    self.get::<T>(id).ok_or(ComponentError::MissingComponent(id, type_name::<T>()))
+  }
+
+  pub fn get_component_mut<T: Component>(&mut self, id: EntityId) -> Result<&mut T, ComponentError> {
+    if !self.is_alive(id) {
+      return Err(ComponentError::NoSuchEntity(id));
+    }
+    self.get_mut::<T>(id).ok_or(ComponentError::MissingComponent(id, type_name::<T>()))
+  }
+
+  /// Sets `id`'s `T`, like `add_component`, but returns the value it
+  /// replaced and refuses to do anything for an entity that is not alive.
+  pub fn insert_component<T: Component>(
+    &mut self,
+    id: EntityId,
+    component: T,
+  ) -> Result<Option<T>, ComponentError> {
+    if !self.is_alive(id) {
+      return Err(ComponentError::NoSuchEntity(id));
+    }
+    self.register_storage::<T>();
+    let previous = self.insert_boxed(id, TypeId::of::<T>(), Box::new(component));
+    Ok(previous.map(|previous| *previous.downcast::<T>().expect("replaced a value of its own type")))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ComponentError;
+  use crate::{query, query_mut, EntityId, World};
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Position(i32, i32);
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Velocity(i32, i32);
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Name(&'static str);
+
+  #[test]
+  fn insert_component_returns_the_replaced_value() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Position(0, 0),));
+
+    assert_eq!(world.insert_component(id, Position(1, 1)), Ok(Some(Position(0, 0))));
+    assert_eq!(world.insert_component(id, Velocity(1, 0)), Ok(None));
+    assert_eq!(world.get_component::<Position>(id), Ok(&Position(1, 1)));
+  }
+
+  #[test]
+  fn insert_component_refuses_dead_entities() {
+    let mut world = World::new();
+    let id = world.spawn();
+    world.despawn(id);
+
+    assert_eq!(world.insert_component(id, Position(0, 0)), Err(ComponentError::NoSuchEntity(id)));
+    assert_eq!(query!(world, Position).count(), 0);
+  }
+
+  #[test]
+  fn inserted_components_join_queries() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Name("crate"), Velocity(1, 2)));
+    world.insert_component(id, Position(0, 0)).unwrap();
+
+    for (_, pos, vel) in query_mut!(world, Position, Velocity) {
+      pos.0 += vel.0;
+      pos.1 += vel.1;
+    }
+    let moved: Vec<(EntityId, Position)> = query!(world, Position).map(|(id, pos)| (id, pos.clone())).collect();
+    assert_eq!(moved, [(id, Position(1, 2))]);
+  }
+
+  #[test]
+  fn get_component_tells_missing_components_apart() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Name("crate"),));
+
+    assert!(matches!(world.get_component::<Position>(id), Err(ComponentError::MissingComponent(found, _)) if found == id));
+    assert!(world.has_component::<Name>(id));
+    world.despawn(id);
+    assert_eq!(world.get_component::<Name>(id), Err(ComponentError::NoSuchEntity(id)));
+  }
+}
@@ -0,0 +1,28 @@
+use crate::{MaybeSync, World};
+
+/// Decides from the state of the world whether systems should run, e.g.
+/// `|world: &World| world.resource::<GameState>() == &GameState::Playing`.
+pub trait Condition: Fn(&World) -> bool + MaybeSync + 'static {
+  fn and(self, other: impl Condition) -> impl Condition
+  where
+    Self: Sized,
+  {
+    move |world: &World| self(world) && other(world)
+  }
+
+  fn or(self, other: impl Condition) -> impl Condition
+  where
+    Self: Sized,
+  {
+    move |world: &World| self(world) || other(world)
+  }
+
+  fn not(self) -> impl Condition
+  where
+    Self: Sized,
+  {
+    move |world: &World| !self(world)
+  }
+}
+
+impl<F: Fn(&World) -> bool + MaybeSync + 'static> Condition for F {}
@@ -0,0 +1,131 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::fmt::{self, Write};
+
+use crate::{Component, EntityId, World};
+
+pub(crate) type DebugFn = fn(&dyn Any, &mut fmt::Formatter) -> fmt::Result;
+
+fn debug_boxed<T: Component + fmt::Debug>(component: &dyn Any, f: &mut fmt::Formatter) -> fmt::Result {
+  let component = component.downcast_ref::<T>().expect("debugger registered for its own type");
+  fmt::Debug::fmt(component, f)
+}
+
+/// One component of an entity, printed through its registered `Debug` impl
+/// or as its type name if there is none.
+struct ComponentDebug<'w> {
+  world: &'w World,
+  id: EntityId,
+  ty: TypeId,
+}
+
+impl fmt::Debug for ComponentDebug<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let component = self.world
+      .get_dyn(self.id, self.ty)
+      .expect("entity has each of its component types");
+    match self.world.debuggers.get(&self.ty) {
+      Some(debug) => debug(component, f),
+      None => write!(f, "<{}>", self.world.archetypes.name(self.ty)),
+    }
+  }
+}
+
+struct EntityDebug<'w> {
+  world: &'w World,
+  id: EntityId,
+}
+
+impl fmt::Debug for EntityDebug<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_list().entries(self.world.components_of(self.id)).finish()
+  }
+}
+
+struct IdDebug(EntityId);
+
+impl fmt::Debug for IdDebug {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+/// Lists entities in spawn order along with their components.
+impl fmt::Debug for World {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_map()
+      .entries(self.entities.iter().map(|&id| (IdDebug(id), EntityDebug { world: self, id })))
+      .finish()
+  }
+}
+
+impl World {
+  /// Lets `Debug` for `World` and `dump` print components of type `T`
+  /// rather than just their type name.
+  pub fn register_debug<T: Component + fmt::Debug>(&mut self) {
+    self.debuggers.insert(TypeId::of::<T>(), debug_boxed::<T>);
+  }
+
+  /// A readable summary of every entity in spawn order, one component per
+  /// line, with the entities separated by dashed lines.
+  pub fn dump(&self) -> String {
+    let rule = "-".repeat(50);
+    let mut dump = String::new();
+    writeln!(dump, "{}", rule).unwrap();
+    for &id in &self.entities {
+      writeln!(dump, "(ID: {})", id).unwrap();
+      for component in self.components_of(id) {
+        writeln!(dump, "  {:?}", component).unwrap();
+      }
+      writeln!(dump, "{}", rule).unwrap();
+    }
+    dump
+  }
+
+  /// The components of `id`, sorted by type name so the order is stable.
+  fn components_of(&self, id: EntityId) -> Vec<ComponentDebug<'_>> {
+    let mut types = self.component_types(id);
+    types.sort_by_key(|&ty| self.archetypes.name(ty));
+    types.into_iter().map(|ty| ComponentDebug { world: self, id, ty }).collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{Name, World};
+
+  #[derive(Debug, PartialEq)]
+  struct Position(i32, i32);
+
+  struct Opaque;
+
+  fn example() -> World {
+    let mut world = World::new();
+    world.register_debug::<Position>();
+    world.register_debug::<Name>();
+    world.spawn_bundle((Position(1, 2),));
+    world.spawn_bundle((Position(3, 4), Opaque));
+    world.spawn_bundle((Position(0, 0), Name::new("Ian")));
+    world
+  }
+
+  #[test]
+  fn dump_lists_every_entity_with_its_components() {
+    let dump = example().dump();
+
+    for expected in ["(ID: 0v0)", "(ID: 1v0)", "(ID: 2v0)", "Position(1, 2)", "Position(3, 4)", "Position(0, 0)", "Name(\"Ian\")"] {
+      assert!(dump.contains(expected), "{} is missing from\n{}", expected, dump);
+    }
+    assert!(dump.contains("<tecs::debug::tests::Opaque>"));
+    assert!(dump.find("Position(1, 2)") < dump.find("Position(3, 4)"));
+  }
+
+  #[test]
+  fn debug_prints_entities_in_spawn_order() {
+    assert_eq!(
+      format!("{:?}", example()),
+      "{0v0: [Position(1, 2)], 1v0: [<tecs::debug::tests::Opaque>, Position(3, 4)], 2v0: [Position(0, 0), Name(\"Ian\")]}"
+    );
+  }
+}
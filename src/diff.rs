@@ -0,0 +1,359 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{ConflictPolicy, EntityId, EntitySnapshot, HashMap, HashSet, SnapshotError, World, WorldSnapshot};
+
+/// What changed between two snapshots of a world, e.g. to roll a world back
+/// or forward by one step. Components are compared by their serialized
+/// value, so only registered components are tracked.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct WorldDiff {
+  pub spawned: Vec<EntitySnapshot>,
+  pub despawned: Vec<EntityId>,
+  /// Entities alive in both snapshots whose components differ.
+  pub changed: Vec<EntityDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityDiff {
+  pub id: EntityId,
+  pub added: BTreeMap<String, Value>,
+  pub removed: Vec<String>,
+  /// Components the entity had before too, with their new value.
+  pub mutated: BTreeMap<String, Value>,
+}
+
+impl WorldDiff {
+  /// The changes that turn `before` into `after`. An id whose generation
+  /// differs counts as a different entity.
+  pub fn compute(before: &WorldSnapshot, after: &WorldSnapshot) -> WorldDiff {
+    let old: HashMap<EntityId, &EntitySnapshot> =
+      before.entities.iter().map(|entity| (entity.id, entity)).collect();
+    let new: HashMap<EntityId, &EntitySnapshot> =
+      after.entities.iter().map(|entity| (entity.id, entity)).collect();
+    let mut diff = WorldDiff {
+      despawned: before.entities
+        .iter()
+        .map(|entity| entity.id)
+        .filter(|id| !new.contains_key(id))
+        .collect(),
+      ..Default::default()
+    };
+    for entity in &after.entities {
+      let Some(previous) = old.get(&entity.id) else {
+        diff.spawned.push(entity.clone());
+        continue;
+      };
+      let mut changes = EntityDiff {
+        id: entity.id,
+        added: BTreeMap::new(),
+        removed: vec![],
+        mutated: BTreeMap::new(),
+      };
+      for (name, value) in &entity.components {
+        match previous.components.get(name) {
+          None => {
+            changes.added.insert(name.clone(), value.clone());
+          }
+          Some(previous) if previous != value => {
+            changes.mutated.insert(name.clone(), value.clone());
+          }
+          Some(_) => {}
+        }
+      }
+      changes.removed = previous.components
+        .keys()
+        .filter(|name| !entity.components.contains_key(*name))
+        .cloned()
+        .collect();
+      if !changes.is_empty() {
+        diff.changed.push(changes);
+      }
+    }
+    diff
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.spawned.is_empty() && self.despawned.is_empty() && self.changed.is_empty()
+  }
+
+  /// Replays the changes on `world`, spawning entities with their recorded
+  /// ids. The world is left untouched if any component fails to load, an
+  /// entity is not in the state the diff expects, or two spawned entities
+  /// share an index.
+  pub fn apply(self, world: &mut World) -> Result<(), SnapshotError> {
+    for &id in self.despawned.iter().chain(self.changed.iter().map(|entity| &entity.id)) {
+      if !world.is_alive(id) {
+        return Err(SnapshotError::NoSuchEntity(id));
+      }
+    }
+    let mut used = HashSet::new();
+    for entity in &self.spawned {
+      let id = entity.id;
+      let replaced = self.despawned
+        .iter()
+        .any(|other| other.index == id.index && other.generation < id.generation);
+      if !used.insert(id.index) || (!replaced && !world.is_free(id)) {
+        return Err(SnapshotError::IdCollision(id));
+      }
+    }
+    let mut inserted = vec![];
+    let loaded = self.spawned
+      .iter()
+      .flat_map(|entity| entity.components.iter().map(move |component| (entity.id, component)))
+      .chain(self.changed.iter().flat_map(|entity| {
+        entity.added.iter().chain(&entity.mutated).map(move |component| (entity.id, component))
+      }));
+    for (id, (name, value)) in loaded {
+      let (ty, component) = world.load_component(name, value)?;
+      inserted.push((id, ty, component));
+    }
+    let mut removed = vec![];
+    for entity in &self.changed {
+      for name in &entity.removed {
+        let ty = world.registered_type(name)?;
+        removed.push((entity.id, ty));
+      }
+    }
+
+    for id in self.despawned {
+      world.despawn(id);
+    }
+    for entity in &self.spawned {
+      world.spawn_at(entity.id);
+    }
+    for (id, ty) in removed {
+      world.remove_boxed(id, ty);
+    }
+    world.deferring_observers(|world| {
+      for (id, ty, component) in inserted {
+        world.insert_boxed(id, ty, component);
+      }
+    });
+    Ok(())
+  }
+}
+
+impl EntityDiff {
+  pub fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty() && self.mutated.is_empty()
+  }
+}
+
+impl WorldSnapshot {
+  /// Reconciles `local` with this snapshot, e.g. an authoritative one from a
+  /// server. `ids` maps the snapshot's ids to local ones, and unmapped ids
+  /// are kept as is. Each entity of the snapshot ends up with the registered
+  /// components it has there: missing ones are added, extra ones removed,
+  /// and `policy` decides between differing values. Entities missing
+  /// locally are spawned, while local entities absent from the snapshot and
+  /// unregistered components are left alone.
+  ///
+  /// `local` is left untouched if any component fails to load, an entity
+  /// that has to be spawned has its id taken, or two entities end up with
+  /// the same index.
+  pub fn diff_apply(
+    &self,
+    local: &mut World,
+    ids: &HashMap<EntityId, EntityId>,
+    policy: ConflictPolicy,
+  ) -> Result<(), SnapshotError> {
+    let mut used = HashSet::new();
+    let mut spawned = vec![];
+    let mut inserted = vec![];
+    let mut removed = vec![];
+    for entity in &self.entities {
+      let id = ids.get(&entity.id).copied().unwrap_or(entity.id);
+      if !used.insert(id.index) {
+        return Err(SnapshotError::IdCollision(id));
+      }
+      let existing = if local.is_alive(id) {
+        local.snapshot_entity(id).components
+      } else if local.is_free(id) {
+        spawned.push(id);
+        BTreeMap::new()
+      } else {
+        return Err(SnapshotError::IdCollision(id));
+      };
+      for (name, value) in &entity.components {
+        let (ty, component) = local.load_component(name, value)?;
+        let replace = match existing.get(name) {
+          None => true,
+          Some(current) if current == value => false,
+          Some(_) => {
+            let current = local.get_dyn(id, ty).expect("snapshotted from the entity");
+            policy.prefers_incoming(ty, current, &*component)
+          }
+        };
+        if replace {
+          inserted.push((id, ty, component));
+        }
+      }
+      for name in existing.keys().filter(|name| !entity.components.contains_key(*name)) {
+        removed.push((id, local.registered_type(name)?));
+      }
+    }
+
+    for id in spawned {
+      local.spawn_at(id);
+    }
+    for (id, ty) in removed {
+      local.remove_boxed(id, ty);
+    }
+    local.deferring_observers(|local| {
+      for (id, ty, component) in inserted {
+        local.insert_boxed(id, ty, component);
+      }
+    });
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct Position(i32, i32);
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct Label(String);
+
+  fn registered() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>("position");
+    world.register_component::<Label>("label");
+    world
+  }
+
+  #[test]
+  fn applying_a_diff_replays_every_kind_of_change() {
+    let mut world = registered();
+    let moved = world.spawn_bundle((Position(0, 0),));
+    let gone = world.spawn_bundle((Position(1, 1),));
+    let relabeled = world.spawn_bundle((Position(2, 2), Label("old".into())));
+    let before = world.snapshot();
+
+    world.get_mut::<Position>(moved).unwrap().0 = 5;
+    world.add_component(moved, Label("new".into()));
+    world.despawn(gone);
+    world.remove_component::<Label>(relabeled);
+    let spawned = world.spawn_bundle((Position(3, 3),));
+    let after = world.snapshot();
+    let diff = WorldDiff::compute(&before, &after);
+
+    assert_eq!(diff.despawned, [gone]);
+    assert_eq!(diff.spawned.iter().map(|entity| entity.id).collect::<Vec<_>>(), [spawned]);
+    assert_eq!(diff.changed.len(), 2);
+    assert_eq!(diff.changed[0].mutated.keys().collect::<Vec<_>>(), ["position"]);
+    assert_eq!(diff.changed[0].added.keys().collect::<Vec<_>>(), ["label"]);
+    assert_eq!(diff.changed[1].removed, ["label"]);
+    assert!(WorldDiff::compute(&after, &after).is_empty());
+
+    let mut replica = registered();
+    replica.restore(&before).unwrap();
+    diff.apply(&mut replica).unwrap();
+    assert_eq!(replica.snapshot(), after);
+  }
+
+  #[test]
+  fn colliding_diffs_leave_the_world_untouched() {
+    let mut world = registered();
+    let id = world.spawn_bundle((Position(0, 0),));
+    let before = world.snapshot();
+    let spawned = |id| EntitySnapshot { id, components: BTreeMap::new() };
+
+    let taken = WorldDiff { spawned: vec![spawned(id)], ..Default::default() };
+    assert!(matches!(taken.apply(&mut world), Err(SnapshotError::IdCollision(taken)) if taken == id));
+
+    let first = EntityId { index: 1, generation: 0 };
+    let second = EntityId { index: 1, generation: 1 };
+    let twice = WorldDiff {
+      spawned: vec![spawned(first), spawned(second)],
+      despawned: vec![id],
+      ..Default::default()
+    };
+    assert!(matches!(twice.apply(&mut world), Err(SnapshotError::IdCollision(taken)) if taken == second));
+    assert_eq!(world.snapshot(), before);
+    assert!(world.is_alive(id));
+  }
+
+  fn entity(id: EntityId, components: &[(&str, Value)]) -> EntitySnapshot {
+    let components = components.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+    EntitySnapshot { id, components }
+  }
+
+  fn server(local: EntityId) -> WorldSnapshot {
+    let remote = EntityId { index: 5, generation: 0 };
+    WorldSnapshot {
+      entities: vec![
+        entity(local, &[("position", serde_json::json!([5, 5]))]),
+        entity(remote, &[("position", serde_json::json!([1, 1])), ("label", serde_json::json!("remote"))]),
+      ],
+    }
+  }
+
+  #[test]
+  fn diff_apply_spawns_missing_entities_and_removes_extra_components() {
+    let mut local = registered();
+    let id = local.spawn_bundle((Position(0, 0), Label("local".into())));
+    let other = local.spawn_bundle((Label("untouched".into()),));
+    server(id).diff_apply(&mut local, &HashMap::new(), ConflictPolicy::KeepExisting).unwrap();
+
+    let remote = EntityId { index: 5, generation: 0 };
+    assert_eq!(local.get::<Position>(id), Some(&Position(0, 0)));
+    assert_eq!(local.get::<Label>(id), None);
+    assert_eq!(local.get::<Position>(remote), Some(&Position(1, 1)));
+    assert_eq!(local.get::<Label>(remote), Some(&Label("remote".into())));
+    assert_eq!(local.get::<Label>(other), Some(&Label("untouched".into())));
+    assert_eq!(local.len(), 3);
+  }
+
+  #[test]
+  fn diff_apply_prefers_incoming_values_when_told_to() {
+    let mut local = registered();
+    let id = local.spawn_bundle((Position(0, 0),));
+    server(id).diff_apply(&mut local, &HashMap::new(), ConflictPolicy::PreferIncoming).unwrap();
+
+    assert_eq!(local.get::<Position>(id), Some(&Position(5, 5)));
+  }
+
+  #[test]
+  fn diff_apply_maps_remote_ids_to_local_ones() {
+    let mut local = registered();
+    local.spawn();
+    let id = local.spawn_bundle((Position(0, 0),));
+    let remote = EntityId { index: 0, generation: 7 };
+    let mut snapshot = server(remote);
+    snapshot.entities.pop();
+    let ids = HashMap::from_iter([(remote, id)]);
+    snapshot.diff_apply(&mut local, &ids, ConflictPolicy::PreferIncoming).unwrap();
+
+    assert_eq!(local.get::<Position>(id), Some(&Position(5, 5)));
+    assert!(!local.is_alive(remote));
+    assert_eq!(local.len(), 2);
+  }
+
+  #[test]
+  fn failed_diff_applies_leave_the_world_untouched() {
+    let mut local = registered();
+    let id = local.spawn_bundle((Position(0, 0), Label("local".into())));
+    let before = local.snapshot();
+    let apply = |local: &mut World, entities| {
+      WorldSnapshot { entities }.diff_apply(local, &HashMap::new(), ConflictPolicy::PreferIncoming)
+    };
+
+    let first = EntityId { index: 3, generation: 0 };
+    let second = EntityId { index: 3, generation: 1 };
+    let twice = vec![entity(id, &[]), entity(first, &[]), entity(second, &[])];
+    assert!(matches!(apply(&mut local, twice), Err(SnapshotError::IdCollision(taken)) if taken == second));
+    let unknown = vec![entity(id, &[]), entity(first, &[("health", serde_json::json!(3))])];
+    assert!(matches!(apply(&mut local, unknown), Err(SnapshotError::UnknownComponent(_))));
+    assert_eq!(local.snapshot(), before);
+  }
+}
@@ -0,0 +1,181 @@
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+
+use crate::{Component, ComponentError, EntityId, World};
+
+/// A borrow of one entity, e.g. for an inspector that lists every entity
+/// with its components without knowing their types.
+#[derive(Clone, Copy)]
+pub struct EntityRef<'w> {
+  world: &'w World,
+  id: EntityId,
+}
+
+impl<'w> EntityRef<'w> {
+  pub fn id(&self) -> EntityId {
+    self.id
+  }
+
+  pub fn get<T: Component>(&self) -> Option<&'w T> {
+    self.world.get::<T>(self.id)
+  }
+
+  pub fn contains<T: Component>(&self) -> bool {
+    self.world.has_component::<T>(self.id)
+  }
+
+  /// The types of both the table and the sparse-set components.
+  pub fn component_types(&self) -> impl Iterator<Item = TypeId> {
+    self.world.component_types(self.id).into_iter()
+  }
+}
+
+/// Yields each component along with its type.
+impl<'w> IntoIterator for EntityRef<'w> {
+  type Item = (TypeId, &'w dyn Any);
+  type IntoIter = alloc::vec::IntoIter<(TypeId, &'w dyn Any)>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    components(self.world, self.id).into_iter()
+  }
+}
+
+/// The mutable twin of `EntityRef`.
+pub struct EntityMut<'w> {
+  world: &'w mut World,
+  id: EntityId,
+}
+
+impl EntityMut<'_> {
+  pub fn id(&self) -> EntityId {
+    self.id
+  }
+
+  pub fn get<T: Component>(&self) -> Option<&T> {
+    self.world.get::<T>(self.id)
+  }
+
+  pub fn get_mut<T: Component>(&mut self) -> Option<&mut T> {
+    self.world.get_mut::<T>(self.id)
+  }
+
+  pub fn contains<T: Component>(&self) -> bool {
+    self.world.has_component::<T>(self.id)
+  }
+
+  pub fn component_types(&self) -> impl Iterator<Item = TypeId> {
+    self.world.component_types(self.id).into_iter()
+  }
+
+  /// Sets the entity's `T`, returning the value it replaced.
+  pub fn insert<T: Component>(&mut self, component: T) -> Option<T> {
+    self.world.insert_component(self.id, component).expect("entity refs are of live entities")
+  }
+
+  pub fn remove<T: Component>(&mut self) -> Option<T> {
+    self.world.remove_component::<T>(self.id)
+  }
+
+  pub fn despawn(self) {
+    self.world.despawn(self.id);
+  }
+}
+
+impl<'a> IntoIterator for &'a EntityMut<'_> {
+  type Item = (TypeId, &'a dyn Any);
+  type IntoIter = alloc::vec::IntoIter<(TypeId, &'a dyn Any)>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    components(self.world, self.id).into_iter()
+  }
+}
+
+fn components(world: &World, id: EntityId) -> Vec<(TypeId, &dyn Any)> {
+  world
+    .component_types(id)
+    .into_iter()
+    .filter_map(|ty| Some((ty, world.get_dyn(id, ty)?)))
+    .collect()
+}
+
+impl World {
+  pub fn entity(&self, id: EntityId) -> Result<EntityRef<'_>, ComponentError> {
+    if !self.is_alive(id) {
+      return Err(ComponentError::NoSuchEntity(id));
+    }
+    Ok(EntityRef { world: self, id })
+  }
+
+  pub fn entity_mut(&mut self, id: EntityId) -> Result<EntityMut<'_>, ComponentError> {
+    if !self.is_alive(id) {
+      return Err(ComponentError::NoSuchEntity(id));
+    }
+    Ok(EntityMut { world: self, id })
+  }
+
+  /// Every entity, in spawn order. There is no mutable counterpart, since
+  /// two `EntityMut`s cannot borrow the world at once; iterate over the ids
+  /// and call `entity_mut` instead.
+  pub fn iter_entities(&self) -> impl Iterator<Item = EntityRef<'_>> {
+    self.entities.iter().map(|&id| EntityRef { world: self, id })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use alloc::vec;
+
+  use super::*;
+  use crate::ComponentError;
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Position(i32, i32);
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Velocity(i32, i32);
+
+  #[test]
+  fn entity_mut_gets_inserts_and_removes_components() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Position(0, 0),));
+    let mut entity = world.entity_mut(id).unwrap();
+
+    assert_eq!(entity.id(), id);
+    entity.get_mut::<Position>().unwrap().0 = 3;
+    assert_eq!(entity.insert(Velocity(1, 1)), None);
+    assert_eq!(entity.insert(Position(4, 4)), Some(Position(3, 0)));
+    assert!(entity.contains::<Velocity>());
+    assert_eq!(entity.remove::<Velocity>(), Some(Velocity(1, 1)));
+    assert_eq!(entity.get::<Velocity>(), None);
+    assert_eq!((&entity).into_iter().count(), 1);
+    entity.despawn();
+
+    assert!(!world.is_alive(id));
+    assert!(matches!(world.entity_mut(id), Err(ComponentError::NoSuchEntity(dead)) if dead == id));
+  }
+
+  #[test]
+  fn entity_refs_iterate_over_every_component() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Position(1, 2), Velocity(3, 4)));
+    let bare = world.spawn();
+    let entity = world.entity(id).unwrap();
+
+    let mut types: Vec<TypeId> = entity.component_types().collect();
+    let mut expected = vec![TypeId::of::<Position>(), TypeId::of::<Velocity>()];
+    types.sort();
+    expected.sort();
+    assert_eq!(types, expected);
+    for (ty, component) in entity {
+      if ty == TypeId::of::<Position>() {
+        assert_eq!(component.downcast_ref::<Position>(), Some(&Position(1, 2)));
+      } else {
+        assert_eq!(component.downcast_ref::<Velocity>(), Some(&Velocity(3, 4)));
+      }
+    }
+    assert_eq!(entity.get::<Position>(), Some(&Position(1, 2)));
+    let ids: Vec<EntityId> = world.iter_entities().map(|entity| entity.id()).collect();
+    assert_eq!(ids, [id, bare]);
+    assert_eq!(world.entity(bare).unwrap().into_iter().count(), 0);
+  }
+}
@@ -0,0 +1,87 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A double-buffered queue of events. Events sent during a frame become
+/// readable once `update` is called at the end of it and are dropped by the
+/// `update` after that, so every reader gets one full frame to see them.
+pub struct Events<T> {
+  events: Vec<T>,
+  pending: Vec<T>,
+}
+
+impl<T> Events<T> {
+  pub fn new() -> Self {
+    Self { events: vec![], pending: vec![] }
+  }
+
+  pub fn send(&mut self, event: T) {
+    self.pending.push(event);
+  }
+
+  /// Swaps the buffers: the events sent since the last update become
+  /// readable and the previously readable ones are dropped.
+  pub fn update(&mut self) {
+    self.events = core::mem::take(&mut self.pending);
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &T> {
+    self.events.iter()
+  }
+
+  pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+    self.events.drain(..)
+  }
+
+  /// Drops both the readable and the pending events.
+  pub fn clear(&mut self) {
+    self.events.clear();
+    self.pending.clear();
+  }
+
+  pub fn len(&self) -> usize {
+    self.events.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.events.is_empty()
+  }
+}
+
+impl<T> Default for Events<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Events;
+
+  #[test]
+  fn events_are_readable_for_one_frame() {
+    let mut events = Events::new();
+    events.send("hit");
+    events.send("miss");
+    assert!(events.is_empty());
+
+    events.update();
+    assert_eq!(events.iter().copied().collect::<Vec<_>>(), ["hit", "miss"]);
+    assert_eq!(events.iter().count(), 2);
+
+    events.update();
+    assert!(events.is_empty());
+  }
+
+  #[test]
+  fn drain_consumes_the_readable_events() {
+    let mut events = Events::new();
+    events.send(1);
+    events.update();
+    events.send(2);
+
+    assert_eq!(events.drain().collect::<Vec<_>>(), [1]);
+    assert!(events.is_empty());
+    events.update();
+    assert_eq!(events.drain().collect::<Vec<_>>(), [2]);
+  }
+}
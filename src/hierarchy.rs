@@ -0,0 +1,142 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+use crate::{EntityId, HashSet, World};
+
+/// Marks an entity as a child of another, e.g. a turret mounted on a ship.
+/// Adding or removing it keeps the parent's `Children` up to date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parent(pub EntityId);
+
+/// The entities whose `Parent` is this entity, in the order they were
+/// parented. Maintained by the world, so it can only be read.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Children(Vec<EntityId>);
+
+impl Deref for Children {
+  type Target = [EntityId];
+
+  fn deref(&self) -> &[EntityId] {
+    &self.0
+  }
+}
+
+/// What happens to the children of a despawned entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DespawnMode {
+  /// Despawns the whole subtree.
+  Recursive,
+  /// Leaves the children alive without a `Parent`.
+  #[default]
+  Orphan,
+}
+
+impl World {
+  /// Makes `child` a child of `parent`, moving it out of the `Children` of
+  /// its previous parent.
+  pub fn set_parent(&mut self, child: EntityId, parent: EntityId) {
+    self.add_component(child, Parent(parent));
+  }
+
+  pub fn children(&self, id: EntityId) -> impl Iterator<Item = EntityId> + '_ {
+    self.get::<Children>(id).into_iter().flat_map(|children| children.iter().copied())
+  }
+
+  pub fn despawn_with(&mut self, id: EntityId, mode: DespawnMode) -> bool {
+    match mode {
+      DespawnMode::Recursive => self.despawn_recursive(id),
+      DespawnMode::Orphan => self.despawn(id),
+    }
+  }
+
+  /// Despawns `id` along with every entity whose `Parent` chain leads back
+  /// to it. Each entity is visited once, so a cycle of `Parent`s cannot loop
+  /// forever. Returns whether `id` was alive.
+  pub fn despawn_recursive(&mut self, id: EntityId) -> bool {
+    if !self.is_alive(id) {
+      return false;
+    }
+    let mut visited = HashSet::from([id]);
+    let mut stack = vec![id];
+    let mut subtree = vec![];
+    while let Some(entity) = stack.pop() {
+      subtree.push(entity);
+      for child in self.children(entity) {
+        if visited.insert(child) {
+          stack.push(child);
+        }
+      }
+    }
+    for entity in subtree {
+      self.despawn(entity);
+    }
+    true
+  }
+
+  pub(crate) fn link_child(&mut self, child: EntityId, parent: EntityId) {
+    match self.get_mut::<Children>(parent) {
+      Some(children) if children.0.contains(&child) => {}
+      Some(children) => children.0.push(child),
+      None => self.add_component(parent, Children(vec![child])),
+    }
+  }
+
+  /// Removes `child` from the `Children` of its current parent.
+  pub(crate) fn unlink_child(&mut self, child: EntityId) {
+    let Some(&Parent(parent)) = self.get::<Parent>(child) else {
+      return;
+    };
+    if let Some(children) = self.get_mut::<Children>(parent) {
+      children.0.retain(|&other| other != child);
+    }
+  }
+
+  /// Removes the `Parent` of every child of `parent`.
+  pub(crate) fn orphan_children(&mut self, parent: EntityId) {
+    for child in self.children(parent).collect::<Vec<_>>() {
+      if self.get::<Parent>(child) == Some(&Parent(parent)) {
+        self.remove_component::<Parent>(child);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::World;
+
+  #[test]
+  fn despawn_recursive_removes_the_whole_subtree() {
+    let mut world = World::new();
+    let ship = world.spawn();
+    let turret = world.spawn();
+    let barrel = world.spawn();
+    let other = world.spawn();
+    world.set_parent(turret, ship);
+    world.set_parent(barrel, turret);
+
+    assert!(world.despawn_recursive(ship));
+
+    for id in [ship, turret, barrel] {
+      assert!(!world.is_alive(id));
+    }
+    assert!(world.is_alive(other));
+    assert!(!world.despawn_recursive(ship));
+  }
+
+  #[test]
+  fn despawn_recursive_stops_at_parent_cycles() {
+    let mut world = World::new();
+    let a = world.spawn();
+    let b = world.spawn();
+    world.set_parent(b, a);
+    world.set_parent(a, b);
+
+    assert!(world.despawn_recursive(a));
+    assert!(!world.is_alive(a));
+    assert!(!world.is_alive(b));
+    assert!(world.is_empty());
+  }
+}
@@ -0,0 +1,1902 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{type_name, Any, TypeId};
+use core::fmt;
+use core::str::FromStr;
+#[cfg(not(feature = "std"))]
+use alloc::collections::{BTreeMap as HashMap, BTreeSet as HashSet};
+#[cfg(not(feature = "parallel"))]
+use alloc::rc::Rc as Shared;
+#[cfg(feature = "parallel")]
+use alloc::sync::Arc as Shared;
+#[cfg(not(feature = "parallel"))]
+use core::cell::RefCell as ObserverQueue;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "parallel")]
+use std::sync::Mutex as ObserverQueue;
+
+use archetype::Archetypes;
+use clone::CloneFn;
+use debug::DebugFn;
+use storage::{SparseSet, Ticks};
+
+#[cfg(feature = "std")]
+mod app;
+mod archetype;
+mod clone;
+mod commands;
+mod component;
+mod condition;
+mod debug;
+#[cfg(feature = "serde")]
+mod diff;
+mod entity;
+mod events;
+mod hierarchy;
+mod merge;
+#[cfg(feature = "serde")]
+mod migration;
+mod name;
+mod observer;
+mod plugin;
+#[cfg(feature = "serde")]
+mod prefab;
+mod previous;
+mod query;
+mod reflect;
+mod relationship;
+mod schedule;
+#[cfg(feature = "serde")]
+mod snapshot;
+mod stats;
+mod storage;
+mod system;
+mod tag;
+
+#[cfg(feature = "std")]
+pub use app::{App, AppExit};
+pub use archetype::Archetype;
+pub use clone::CloneError;
+pub use commands::{Command, Commands};
+pub use component::ComponentError;
+pub use condition::Condition;
+pub use entity::{EntityMut, EntityRef};
+pub use events::Events;
+pub use hierarchy::{Children, DespawnMode, Parent};
+pub use merge::{ConflictPolicy, MergeError, MergeResult};
+pub use name::Name;
+pub use observer::ObserverId;
+pub use plugin::{DefaultPlugins, FrameCount, Plugin};
+#[cfg(feature = "serde")]
+pub use prefab::Prefab;
+#[doc(hidden)]
+pub use query::{
+  order_by as __order_by, order_by_key as __order_by_key, permute as __permute, single as __single,
+  QueryItem,
+};
+pub use query::{
+  Added, Changed, Query, QueryData, QueryFilter, QuerySingleError, Tagged, With, Without, WorldQuery,
+};
+pub use reflect::{ComponentInfo, FormatFn, Reflect, ReflectRegistry};
+pub use relationship::Relationship;
+pub use schedule::{
+  FixedTime, ParallelismMode, Schedule, ScheduleError, ShouldRun, Stage, SystemSet,
+};
+#[cfg(feature = "serde")]
+pub use diff::{EntityDiff, WorldDiff};
+#[cfg(feature = "serde")]
+pub use snapshot::{EntitySnapshot, SnapshotError, WorldSnapshot};
+pub use stats::WorldStats;
+pub use storage::StorageKind;
+pub use system::{
+  Access, Exclusive, ExclusiveSystem, FunctionSystem, IntoSystem, Local, Res, ResMut, System,
+  SystemParam, SystemParamItem,
+};
+pub use tag::Tag;
+pub use tecs_derive::{Bundle, Components, Reflect};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntityId {
+  index: u32,
+  generation: u32,
+}
+
+impl EntityId {
+  pub fn index(&self) -> u32 {
+    self.index
+  }
+
+  pub fn generation(&self) -> u32 {
+    self.generation
+  }
+
+  /// The id packed into one number, e.g. to send it over the network.
+  pub fn raw(&self) -> u64 {
+    (self.generation as u64) << 32 | self.index as u64
+  }
+
+  pub fn from_raw(raw: u64) -> Self {
+    Self { index: raw as u32, generation: (raw >> 32) as u32 }
+  }
+}
+
+impl fmt::Display for EntityId {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}v{}", self.index, self.generation)
+  }
+}
+
+/// The error of parsing an `EntityId` that is not in the `3v1` form it is
+/// displayed in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEntityIdError;
+
+impl fmt::Display for ParseEntityIdError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "entity ids look like `<index>v<generation>`")
+  }
+}
+
+impl core::error::Error for ParseEntityIdError {}
+
+impl FromStr for EntityId {
+  type Err = ParseEntityIdError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (index, generation) = s.split_once('v').ok_or(ParseEntityIdError)?;
+    Ok(Self {
+      index: index.parse().map_err(|_| ParseEntityIdError)?,
+      generation: generation.parse().map_err(|_| ParseEntityIdError)?,
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpawnError {
+  /// As many entities as `World::set_entity_limit` allows are alive.
+  AtCapacity(usize),
+}
+
+impl fmt::Display for SpawnError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SpawnError::AtCapacity(max) => write!(f, "the world is at its limit of {} entities", max),
+    }
+  }
+}
+
+impl core::error::Error for SpawnError {}
+
+/// `Send` with the `parallel` feature, so that systems and the data they
+/// hold can move to other threads, and no bound at all without it.
+#[cfg(feature = "parallel")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "parallel")]
+impl<T: Send> MaybeSend for T {}
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "parallel"))]
+impl<T> MaybeSend for T {}
+
+/// `Send + Sync` with the `parallel` feature, so that systems running at the
+/// same time can share the world, and no bound at all without it.
+#[cfg(feature = "parallel")]
+pub trait MaybeSync: Send + Sync {}
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> MaybeSync for T {}
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "parallel"))]
+impl<T> MaybeSync for T {}
+
+/// `HashMap::get_disjoint_mut` for the `BTreeMap` that stands in for it
+/// without `std`. Every key has to be distinct.
+#[cfg(not(feature = "std"))]
+trait GetDisjointMut<K, V> {
+  fn get_disjoint_mut<const N: usize>(&mut self, keys: [&K; N]) -> [Option<&mut V>; N];
+}
+
+#[cfg(not(feature = "std"))]
+impl<K: Ord, V> GetDisjointMut<K, V> for HashMap<K, V> {
+  fn get_disjoint_mut<const N: usize>(&mut self, keys: [&K; N]) -> [Option<&mut V>; N] {
+    let mut values = [const { None }; N];
+    for (key, value) in self.iter_mut() {
+      if let Some(i) = keys.iter().position(|other| *other == key) {
+        values[i] = Some(value);
+      }
+    }
+    values
+  }
+}
+
+/// Any `'static` type can be a component. Unit structs such as `struct
+/// Player;` work as tags: queries match them by presence alone, and the
+/// columns holding them never allocate.
+pub trait Component: MaybeSync + 'static {}
+
+impl<T: MaybeSync + 'static> Component for T {}
+
+pub trait Resource: MaybeSync + 'static {}
+
+impl<T: MaybeSync + 'static> Resource for T {}
+
+#[cfg(not(feature = "parallel"))]
+type AnyResource = dyn Any;
+#[cfg(feature = "parallel")]
+type AnyResource = dyn Any + Send + Sync;
+
+pub trait Bundle {
+  fn insert_into(self, world: &mut World, id: EntityId);
+
+  /// Spawns an entity per bundle for `World::spawn_batch`.
+  #[doc(hidden)]
+  fn spawn_batch(world: &mut World, bundles: impl Iterator<Item = Self>) -> Vec<EntityId>
+  where
+    Self: Sized,
+  {
+    bundles.map(|bundle| world.spawn_bundle(bundle)).collect()
+  }
+}
+
+macro_rules! impl_bundle {
+  ($($name:ident),+) => {
+    impl<$($name: Component),+> Bundle for ($($name,)+) {
+      #[allow(non_snake_case)]
+      fn insert_into(self, world: &mut World, id: EntityId) {
+        let ($($name,)+) = self;
+        $(world.add_component(id, $name);)+
+      }
+
+      /// Writes the components straight into the columns of the archetype
+      /// they all end up in, unless that would skip work `add_component`
+      /// does for them.
+      #[allow(non_snake_case)]
+      fn spawn_batch(world: &mut World, bundles: impl Iterator<Item = Self>) -> Vec<EntityId> {
+        $(world.register_storage::<$name>();)+
+        let types = [$(TypeId::of::<$name>()),+];
+        if !world.can_push_columns(&types) {
+          return bundles.map(|bundle| world.spawn_bundle(bundle)).collect();
+        }
+        let archetype = world.archetypes.archetype_for(types.iter().copied().collect());
+        bundles
+          .map(|($($name,)+)| {
+            let id = world.spawn_into(archetype, &types);
+            let mut columns = world.archetypes.columns_mut(archetype, types.each_ref()).into_iter();
+            $(storage::push(columns.next().expect("a column per type").as_mut(), $name);)+
+            id
+          })
+          .collect()
+      }
+    }
+  };
+}
+
+impl_bundle!(A);
+impl_bundle!(A, B);
+impl_bundle!(A, B, C);
+
+#[cfg(not(feature = "parallel"))]
+type Hook = Shared<dyn Fn(&mut World, EntityId)>;
+#[cfg(feature = "parallel")]
+type Hook = Shared<dyn Fn(&mut World, EntityId) + Send + Sync>;
+
+pub type StorageMut<'w> = HashMap<EntityId, &'w mut dyn Any>;
+
+/// Mutable borrows of several component storages at once, as used by
+/// `query_mut!`. Every entity fetched through it is marked as changed for each
+/// of the borrowed component types.
+pub struct StoragesMut<'w, const N: usize> {
+  storages: [StorageMut<'w>; N],
+  changes: [&'w mut Ticks; N],
+  tick: u32,
+}
+
+impl<'w, const N: usize> StoragesMut<'w, N> {
+  pub fn fetch(&mut self, id: EntityId) -> Option<[&'w mut dyn Any; N]> {
+    if !self.storages.iter().all(|storage| storage.contains_key(&id)) {
+      return None;
+    }
+    for changed in &mut self.changes {
+      changed.borrow_mut(id, self.tick);
+    }
+    Some(self.storages.each_mut().map(|storage| storage.remove(&id).unwrap()))
+  }
+}
+
+pub struct World {
+  entities: Vec<EntityId>,
+  generations: Vec<u32>,
+  free: Vec<u32>,
+  // When each live entity was spawned, by index, to sort query results by.
+  spawn_order: Vec<u64>,
+  spawned: u64,
+  reserved: HashSet<EntityId>,
+  components: HashMap<TypeId, SparseSet>,
+  resources: HashMap<TypeId, Box<AnyResource>>,
+  // The type name of every resource ever inserted, for errors.
+  resource_names: HashMap<TypeId, &'static str>,
+  changes: HashMap<TypeId, Ticks>,
+  tick: u32,
+  added: HashSet<(EntityId, TypeId)>,
+  archetypes: Archetypes,
+  on_add: HashMap<TypeId, Hook>,
+  on_remove: HashMap<TypeId, Hook>,
+  cloners: HashMap<TypeId, CloneFn>,
+  debuggers: HashMap<TypeId, DebugFn>,
+  observers: HashMap<TypeId, Vec<(ObserverId, observer::Observer)>>,
+  next_observer: u64,
+  // Behind a mutex under `parallel` only to keep the world `Sync`; always
+  // reached through `&mut self`.
+  observed: ObserverQueue<Commands>,
+  observing: u32,
+  cascades: HashMap<TypeId, relationship::CascadeFn>,
+  names: HashMap<String, Vec<EntityId>>,
+  tags: tag::TagStorage,
+  plugins: HashSet<TypeId>,
+  entity_limit: Option<usize>,
+  previous: HashMap<TypeId, Box<AnyResource>>,
+  snapshots: HashMap<TypeId, previous::Tracker>,
+  #[cfg(feature = "serde")]
+  registered: HashMap<&'static str, TypeId>,
+  #[cfg(feature = "serde")]
+  registrations: HashMap<TypeId, snapshot::Registration>,
+  #[cfg(feature = "serde")]
+  migrations: HashMap<TypeId, migration::Migration>,
+}
+
+impl World {
+  pub fn new() -> Self {
+    Self {
+      entities: vec![],
+      generations: vec![],
+      free: vec![],
+      spawn_order: vec![],
+      spawned: 0,
+      reserved: HashSet::new(),
+      components: HashMap::new(),
+      resources: HashMap::new(),
+      resource_names: HashMap::new(),
+      changes: HashMap::new(),
+      tick: 0,
+      added: HashSet::new(),
+      archetypes: Archetypes::new(),
+      on_add: HashMap::new(),
+      on_remove: HashMap::new(),
+      cloners: HashMap::new(),
+      debuggers: HashMap::new(),
+      observers: HashMap::new(),
+      next_observer: 0,
+      observed: ObserverQueue::default(),
+      observing: 0,
+      cascades: HashMap::new(),
+      names: HashMap::new(),
+      tags: HashMap::new(),
+      plugins: HashSet::new(),
+      entity_limit: None,
+      previous: HashMap::new(),
+      snapshots: HashMap::new(),
+      #[cfg(feature = "serde")]
+      registered: HashMap::new(),
+      #[cfg(feature = "serde")]
+      registrations: HashMap::new(),
+      #[cfg(feature = "serde")]
+      migrations: HashMap::new(),
+    }
+  }
+
+  /// A world with room for `capacity` entities before it has to reallocate
+  /// its entity bookkeeping, e.g. ahead of loading a level.
+  pub fn with_capacity(capacity: usize) -> Self {
+    let mut world = Self::new();
+    world.entities.reserve(capacity);
+    world.generations.reserve(capacity);
+    world.archetypes.reserve(capacity);
+    world
+  }
+
+  /// How many entities fit before the world has to reallocate.
+  pub fn capacity(&self) -> usize {
+    self.entities.capacity()
+  }
+
+  pub fn entities(&self) -> &[EntityId] {
+    &self.entities
+  }
+
+  pub fn len(&self) -> usize {
+    self.entities.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entities.is_empty()
+  }
+
+  pub fn archetypes(&self) -> &[Archetype] {
+    self.archetypes.as_slice()
+  }
+
+  /// Entities that have every component in `types`, always in spawn order
+  /// so that iterating queries is reproducible, e.g. for replays. Table
+  /// components are matched archetype by archetype so entities missing any of
+  /// them are never looked at; if sparse-set components are requested, the
+  /// smallest of their sets is walked instead. Only the matches are sorted.
+  pub fn matching_entities(&self, types: Vec<TypeId>) -> impl Iterator<Item = EntityId> + '_ {
+    let known = types
+      .iter()
+      .all(|ty| self.archetypes.is_table(*ty) || self.archetypes.is_sparse(*ty));
+    if !known {
+      // No entity has ever had a component of an unknown type.
+      return vec![].into_iter();
+    }
+    let (table, sparse): (Vec<_>, Vec<_>) =
+      types.into_iter().partition(|ty| self.archetypes.is_table(*ty));
+    // A sparse set is missing while a system queries it mutably, in which
+    // case the query itself skips entities without it.
+    let mut sets: Vec<&SparseSet> = sparse.iter().filter_map(|ty| self.components.get(ty)).collect();
+    sets.sort_by_key(|set| set.len());
+    let mut entities: Vec<EntityId> = match sets.split_first() {
+      None => self.archetypes.matching(&table).collect(),
+      Some((smallest, rest)) => {
+        // Matched once per archetype, so that each entity costs a lookup
+        // rather than a search of its types.
+        let matches: Vec<bool> =
+          self.archetypes.as_slice().iter().map(|archetype| archetype.contains_all(&table)).collect();
+        smallest
+          .entities()
+          .iter()
+          .copied()
+          .filter(|&id| matches[self.archetypes.archetype_of(id)] && rest.iter().all(|set| set.contains(id)))
+          .collect()
+      }
+    };
+    entities.sort_by_key(|id| self.spawn_order[id.index as usize]);
+    entities.into_iter()
+  }
+
+  /// The entities that have a `T`, in spawn order. Only storages are
+  /// checked; no component is fetched.
+  pub fn entities_with<T: Component>(&self) -> impl Iterator<Item = EntityId> + '_ {
+    self.matching_entities(vec![TypeId::of::<T>()])
+  }
+
+  /// The untyped counterpart of `query!`, e.g. for a scripting layer: yields
+  /// every entity that has all of `types`, with its components in the same
+  /// order, to be downcast by the caller.
+  pub fn query_raw<'w>(
+    &'w self,
+    types: &'w [TypeId],
+  ) -> impl Iterator<Item = (EntityId, Vec<&'w dyn Any>)> + 'w {
+    self.matching_entities(types.to_vec()).filter_map(move |id| {
+      let components = types.iter().map(|ty| self.get_dyn(id, *ty)).collect::<Option<_>>()?;
+      Some((id, components))
+    })
+  }
+
+  /// The only entity with a `T`, or `None` if there is none. Panics if more
+  /// than one entity has a `T`.
+  pub fn query_single<T: Component>(&self) -> Option<(EntityId, &T)> {
+    match self.try_query_single::<T>() {
+      Ok(single) => Some(single),
+      Err(QuerySingleError::NoEntities(_)) => None,
+      Err(error) => panic!("{}", error),
+    }
+  }
+
+  pub fn try_query_single<T: Component>(&self) -> Result<(EntityId, &T), QuerySingleError> {
+    let entities = self.matching_entities(vec![TypeId::of::<T>()]);
+    query::single(entities.filter_map(|id| Some((id, self.get::<T>(id)?))), type_name::<T>())
+  }
+
+  /// Panics if the world is at its entity limit; see `try_spawn`.
+  pub fn spawn(&mut self) -> EntityId {
+    let id = self.alloc();
+    self.archetypes.insert(id);
+    id
+  }
+
+  /// Spawns an entity into `archetype`, whose table components of `types`
+  /// the caller pushes right after, e.g. for `spawn_batch`.
+  fn spawn_into(&mut self, archetype: usize, types: &[TypeId]) -> EntityId {
+    let id = self.alloc();
+    self.archetypes.insert_into(id, archetype);
+    for &ty in types {
+      self.added.insert((id, ty));
+      self.changes.entry(ty).or_default().insert(id, self.tick);
+    }
+    id
+  }
+
+  /// Whether components of `types` can be pushed straight into their
+  /// columns: each is a distinct table component that no hook, observer,
+  /// hierarchy or name index has to hear about.
+  fn can_push_columns(&self, types: &[TypeId]) -> bool {
+    types.iter().enumerate().all(|(i, ty)| {
+      !types[..i].contains(ty)
+        && self.archetypes.is_table(*ty)
+        && !self.on_add.contains_key(ty)
+        && !self.observers.contains_key(ty)
+        && *ty != TypeId::of::<Parent>()
+        && *ty != TypeId::of::<Name>()
+    })
+  }
+
+  /// A new id, counted among the entities but not in any archetype yet.
+  fn alloc(&mut self) -> EntityId {
+    if let Err(error) = self.check_capacity() {
+      panic!("{}", error);
+    }
+    let id = match self.free.pop() {
+      Some(index) => EntityId {
+        index,
+        generation: self.generations[index as usize],
+      },
+      None => {
+        self.generations.push(0);
+        EntityId {
+          index: self.generations.len() as u32 - 1,
+          generation: 0,
+        }
+      }
+    };
+    self.push_entity(id);
+    id
+  }
+
+  /// Adds `id` to the live entities, after every entity already alive.
+  fn push_entity(&mut self, id: EntityId) {
+    let index = id.index as usize;
+    if index >= self.spawn_order.len() {
+      self.spawn_order.resize(index + 1, 0);
+    }
+    self.spawn_order[index] = self.spawned;
+    self.spawned += 1;
+    self.entities.push(id);
+  }
+
+  /// Caps how many entities can be alive at once. Spawning beyond it makes
+  /// `try_spawn` fail and `spawn` panic; entities already alive are kept.
+  pub fn set_entity_limit(&mut self, max: usize) {
+    self.entity_limit = Some(max);
+  }
+
+  /// Like `spawn_bundle`, but fails instead of panicking at the entity limit.
+  pub fn try_spawn(&mut self, bundle: impl Bundle) -> Result<EntityId, SpawnError> {
+    self.check_capacity()?;
+    Ok(self.spawn_bundle(bundle))
+  }
+
+  fn check_capacity(&self) -> Result<(), SpawnError> {
+    match self.entity_limit {
+      Some(max) if self.entities.len() >= max => Err(SpawnError::AtCapacity(max)),
+      _ => Ok(()),
+    }
+  }
+
+  pub fn spawn_bundle(&mut self, bundle: impl Bundle) -> EntityId {
+    let id = self.spawn();
+    self.deferring_observers(|world| bundle.insert_into(world, id));
+    id
+  }
+
+  /// Spawns an entity per bundle, reserving room for all of them up front
+  /// from the iterator's size hint. Tuples of table components are written
+  /// straight into their archetype's columns.
+  pub fn spawn_batch<B: Bundle>(&mut self, bundles: impl IntoIterator<Item = B>) -> Vec<EntityId> {
+    let bundles = bundles.into_iter();
+    let (additional, _) = bundles.size_hint();
+    self.entities.reserve(additional);
+    self.generations.reserve(additional.saturating_sub(self.free.len()));
+    B::spawn_batch(self, bundles)
+  }
+
+  /// Spawns an entity without components whose id can be handed out before
+  /// they are ready, e.g. to link two entities to each other. It counts as
+  /// reserved until its first component is added.
+  pub fn reserve_entity(&mut self) -> EntityId {
+    let id = self.spawn();
+    self.reserved.insert(id);
+    id
+  }
+
+  pub fn is_reserved(&self, id: EntityId) -> bool {
+    self.reserved.contains(&id)
+  }
+
+  /// Despawns every entity that was reserved but never given a component,
+  /// returning how many there were.
+  pub fn flush_reserved(&mut self) -> usize {
+    let reserved: Vec<_> = core::mem::take(&mut self.reserved).into_iter().collect();
+    for &id in &reserved {
+      self.despawn(id);
+    }
+    reserved.len()
+  }
+
+  pub fn is_alive(&self, id: EntityId) -> bool {
+    self.generations.get(id.index as usize) == Some(&id.generation)
+  }
+
+  /// Whether `id` is one of this world's entities; the same as `is_alive`.
+  pub fn contains(&self, id: EntityId) -> bool {
+    self.is_alive(id)
+  }
+
+  pub fn add_component<T: Component>(&mut self, id: EntityId, component: T) {
+    self.register_storage::<T>();
+    self.insert_boxed(id, TypeId::of::<T>(), Box::new(component));
+  }
+
+  /// Gives `T` the default storage unless it already has one.
+  fn register_storage<T: Component>(&mut self) {
+    let ty = TypeId::of::<T>();
+    if !self.archetypes.is_sparse(ty) && !self.archetypes.is_table(ty) {
+      self.archetypes.set_table(ty, type_name::<T>(), Some(storage::new_column::<T>));
+    }
+  }
+
+  /// Inserts a component whose storage was already chosen, either explicitly
+  /// or by `add_component`, and returns the one it replaced.
+  fn insert_boxed(&mut self, id: EntityId, ty: TypeId, component: Box<dyn Any>) -> Option<Box<dyn Any>> {
+    if !self.is_alive(id) {
+      return None;
+    }
+    self.reserved.remove(&id);
+    let parent = component.downcast_ref::<Parent>().map(|&Parent(parent)| parent);
+    if parent.is_some() {
+      self.unlink_child(id);
+    }
+    let name = component.downcast_ref::<Name>().map(|name| name.as_str().to_owned());
+    if name.is_some() {
+      self.unindex_name(id);
+    }
+    let previous = if let Some(set) = self.components.get_mut(&ty) {
+      set.insert(id, component)
+    } else if self.archetypes.types_of(id).contains(&ty) {
+      self.archetypes.replace(id, ty, component)
+    } else {
+      self.archetypes.add_type(id, ty, component);
+      None
+    };
+    let added = previous.is_none();
+    if added {
+      self.added.insert((id, ty));
+    }
+    self.changes.entry(ty).or_default().insert(id, self.tick);
+    if let Some(parent) = parent {
+      self.link_child(id, parent);
+    }
+    if let Some(name) = name {
+      self.index_name(id, name);
+    }
+    if added {
+      if let Some(hook) = self.on_add.get(&ty).cloned() {
+        hook(self, id);
+      }
+    }
+    self.notify(id, ty);
+    previous
+  }
+
+  pub fn remove_component<T: Component>(&mut self, id: EntityId) -> Option<T> {
+    let component = self.remove_boxed(id, TypeId::of::<T>())?;
+    component.downcast::<T>().ok().map(|component| *component)
+  }
+
+  fn remove_boxed(&mut self, id: EntityId, ty: TypeId) -> Option<Box<dyn Any>> {
+    if !self.has(id, ty) {
+      return None;
+    }
+    if let Some(hook) = self.on_remove.get(&ty).cloned() {
+      hook(self, id);
+    }
+    if ty == TypeId::of::<Parent>() {
+      self.unlink_child(id);
+    }
+    if ty == TypeId::of::<Name>() {
+      self.unindex_name(id);
+    }
+    if let Some(changed) = self.changes.get_mut(&ty) {
+      changed.remove(id);
+    }
+    self.added.remove(&(id, ty));
+    self.take_boxed(id, ty)
+  }
+
+  /// Removes a component without running hooks or updating change tracking.
+  fn take_boxed(&mut self, id: EntityId, ty: TypeId) -> Option<Box<dyn Any>> {
+    match self.components.get_mut(&ty) {
+      Some(set) => set.remove(id),
+      None => self.archetypes.remove_type(id, ty),
+    }
+  }
+
+  fn has(&self, id: EntityId, ty: TypeId) -> bool {
+    if !self.is_alive(id) {
+      return false;
+    }
+    match self.components.get(&ty) {
+      Some(set) => set.contains(id),
+      None => self.archetypes.types_of(id).contains(&ty),
+    }
+  }
+
+  pub fn get<T: Component>(&self, id: EntityId) -> Option<&T> {
+    if !self.is_alive(id) {
+      return None;
+    }
+    self.get_dyn(id, TypeId::of::<T>())?.downcast_ref::<T>()
+  }
+
+  fn get_dyn(&self, id: EntityId, ty: TypeId) -> Option<&dyn Any> {
+    match self.components.get(&ty) {
+      Some(set) => set.get(id),
+      None => self.archetypes.get(id, ty),
+    }
+  }
+
+  /// The types of both the table and the sparse-set components of `id`.
+  fn component_types(&self, id: EntityId) -> Vec<TypeId> {
+    let mut types: Vec<TypeId> = self.archetypes.types_of(id).iter().copied().collect();
+    types.extend(self.components.iter().filter(|(_, set)| set.contains(id)).map(|(ty, _)| *ty));
+    types
+  }
+
+  /// Changes `id`'s `T` through `update`, keeping the name index in sync and
+  /// running the observers of `T`. Returns whether `id` had a `T`.
+  pub fn update<T: Component>(&mut self, id: EntityId, update: impl FnOnce(&mut T)) -> bool {
+    let renamed = TypeId::of::<T>() == TypeId::of::<Name>();
+    if renamed {
+      self.unindex_name(id);
+    }
+    let Some(component) = self.get_mut::<T>(id) else {
+      return false;
+    };
+    update(component);
+    if renamed {
+      self.reindex_name(id);
+    }
+    self.notify(id, TypeId::of::<T>());
+    true
+  }
+
+  pub fn get_mut<T: Component>(&mut self, id: EntityId) -> Option<&mut T> {
+    if !self.is_alive(id) {
+      return None;
+    }
+    let ty = TypeId::of::<T>();
+    let component = match self.components.get_mut(&ty) {
+      Some(set) => set.get_mut(id)?,
+      None => self.archetypes.get_mut(id, ty)?,
+    }
+    .downcast_mut::<T>()?;
+    self.changes.entry(ty).or_default().borrow_mut(id, self.tick);
+    Some(component)
+  }
+
+  /// Whether `T` was added to or mutably borrowed on `id` since the last
+  /// call to `clear_trackers`.
+  pub fn is_changed<T: Component>(&self, id: EntityId) -> bool {
+    self.is_changed_since::<T>(id, self.tick)
+  }
+
+  /// Whether `T` was added to or mutably borrowed on `id` at or after `tick`,
+  /// as previously returned by `change_tick`.
+  pub fn is_changed_since<T: Component>(&self, id: EntityId, tick: u32) -> bool {
+    self.last_changed::<T>(id).is_some_and(|changed| changed >= tick)
+  }
+
+  /// The tick at which `T` was last added to or mutably borrowed on `id`.
+  pub fn last_changed<T: Component>(&self, id: EntityId) -> Option<u32> {
+    self.changes.get(&TypeId::of::<T>())?.get(id)
+  }
+
+  /// The tick that changes are currently recorded at. It advances on every
+  /// call to `clear_trackers`.
+  pub fn change_tick(&self) -> u32 {
+    self.tick
+  }
+
+  /// Whether `T` was added to `id` since the last call to `clear_trackers`.
+  /// Replacing an existing component does not count as adding it.
+  pub fn is_added<T: Component>(&self, id: EntityId) -> bool {
+    self.added.contains(&(id, TypeId::of::<T>()))
+  }
+
+  /// Resets both the changed and the added tracking, typically once per
+  /// frame. Change ticks are kept, so `is_changed_since` still sees changes
+  /// from earlier frames. Also records the values `previous` returns, and
+  /// then runs the observers of values borrowed mutably in place, whose own
+  /// changes count towards the new frame.
+  pub fn clear_trackers(&mut self) {
+    self.snapshot_previous();
+    self.tick += 1;
+    self.added.clear();
+    self.notify_mutated();
+  }
+
+  pub fn storages_mut<const N: usize>(
+    &mut self,
+    types: [(TypeId, &'static str); N],
+  ) -> (impl Iterator<Item = EntityId> + '_, StoragesMut<'_, N>) {
+    for (i, (ty, name)) in types.iter().enumerate() {
+      if types[..i].iter().any(|(other, _)| other == ty) {
+        panic!("component `{}` was requested more than once in the same query", name);
+      }
+      self.changes.entry(*ty).or_default();
+    }
+    let types = types.each_ref().map(|(ty, _)| ty);
+    let table: Vec<TypeId> = types.iter().copied().copied().filter(|ty| self.archetypes.is_table(*ty)).collect();
+    let mut storages: [StorageMut; N] = self.components
+      .get_disjoint_mut(types)
+      .map(|set| set.into_iter().flat_map(|set| set.iter_mut()).collect());
+    self.archetypes.extend_storages(&table, types, &mut storages);
+    // An entity has to be in every storage, so walking the smallest one is
+    // enough. Its keys are sorted back into spawn order.
+    let mut entities: Vec<EntityId> = match storages.iter().min_by_key(|storage| storage.len()) {
+      Some(smallest) => smallest
+        .keys()
+        .copied()
+        .filter(|id| storages.iter().all(|storage| storage.contains_key(id)))
+        .collect(),
+      None => self.entities.clone(),
+    };
+    let spawn_order = &self.spawn_order;
+    entities.sort_by_key(|id| spawn_order[id.index as usize]);
+    let changes = self.changes
+      .get_disjoint_mut(types)
+      .map(|changed| changed.expect("change sets exist for every queried type"));
+    (entities.into_iter(), StoragesMut { storages, changes, tick: self.tick })
+  }
+
+  pub fn despawn(&mut self, id: EntityId) -> bool {
+    if !self.is_alive(id) {
+      return false;
+    }
+    for ty in self.component_types(id) {
+      if !self.is_alive(id) {
+        return true;
+      }
+      if let Some(hook) = self.on_remove.get(&ty).cloned() {
+        hook(self, id);
+      }
+    }
+    if !self.is_alive(id) {
+      return true;
+    }
+    self.unlink_child(id);
+    self.orphan_children(id);
+    self.unindex_name(id);
+    self.untag_all(id);
+    self.reserved.remove(&id);
+    self.entities.retain(|entity| *entity != id);
+    self.archetypes.remove(id);
+    for set in self.components.values_mut() {
+      set.remove(id);
+    }
+    for changed in self.changes.values_mut() {
+      changed.remove(id);
+    }
+    self.added.retain(|(entity, _)| *entity != id);
+    let generation = &mut self.generations[id.index as usize];
+    *generation = generation.wrapping_add(1);
+    self.free.push(id.index);
+    for cascade in self.cascades.values().copied().collect::<Vec<_>>() {
+      cascade(self, id);
+    }
+    true
+  }
+
+  /// Despawns every entity for which `keep` returns false, e.g.
+  /// `world.retain(|id, world| world.has_component::<Position>(id))`. Every
+  /// entity is tested before any is despawned.
+  pub fn retain(&mut self, mut keep: impl FnMut(EntityId, &World) -> bool) {
+    let doomed: Vec<EntityId> = self.entities.iter().copied().filter(|&id| !keep(id, self)).collect();
+    self.despawn_all(doomed);
+  }
+
+  /// The opposite of `retain`: despawns every entity for which `doomed`
+  /// returns true, e.g. enemies out of health, and returns how many were
+  /// despawned. `doomed` may look at the rest of the world, which stays
+  /// untouched until every entity has been tested. Entities despawned along
+  /// the way by hooks or relationship cascades are not counted.
+  pub fn despawn_where(&mut self, doomed: impl Fn(EntityId, &World) -> bool) -> usize {
+    let doomed: Vec<EntityId> = self.entities.iter().copied().filter(|&id| doomed(id, self)).collect();
+    self.despawn_all(doomed)
+  }
+
+  /// Despawns `ids`, returning how many of them were still alive by the
+  /// time their turn came.
+  fn despawn_all(&mut self, ids: Vec<EntityId>) -> usize {
+    ids.into_iter().filter(|&id| self.despawn(id)).count()
+  }
+
+  /// Despawns every entity at once while keeping the memory allocated for
+  /// them, e.g. to reload a level. Resources and everything registered with
+  /// the world stay. Ids from before the clear are no longer alive. Unlike
+  /// `despawn`, no `on_remove` hooks run.
+  pub fn clear_entities(&mut self) {
+    for id in self.entities.drain(..) {
+      let generation = &mut self.generations[id.index as usize];
+      *generation = generation.wrapping_add(1);
+      self.free.push(id.index);
+    }
+    self.reserved.clear();
+    self.names.clear();
+    self.tags.clear();
+    self.archetypes.clear();
+    for set in self.components.values_mut() {
+      set.clear();
+    }
+    for changed in self.changes.values_mut() {
+      changed.clear();
+    }
+    self.added.clear();
+    self.previous.clear();
+  }
+
+  /// Resets the world to the state of `World::new`, dropping entities,
+  /// resources, hooks and registrations, but reusing what allocations it can.
+  /// Generations start over too, so ids from before the clear may come back
+  /// for new entities.
+  pub fn clear(&mut self) {
+    self.entities.clear();
+    self.generations.clear();
+    self.free.clear();
+    self.spawn_order.clear();
+    self.spawned = 0;
+    self.reserved.clear();
+    self.components.clear();
+    self.resources.clear();
+    self.resource_names.clear();
+    self.changes.clear();
+    self.tick = 0;
+    self.added.clear();
+    self.archetypes = Archetypes::new();
+    self.on_add.clear();
+    self.on_remove.clear();
+    self.cloners.clear();
+    self.debuggers.clear();
+    self.observers.clear();
+    self.observed = ObserverQueue::default();
+    self.observing = 0;
+    self.cascades.clear();
+    self.names.clear();
+    self.tags.clear();
+    self.plugins.clear();
+    self.entity_limit = None;
+    self.previous.clear();
+    self.snapshots.clear();
+    #[cfg(feature = "serde")]
+    {
+      self.registered.clear();
+      self.registrations.clear();
+      self.migrations.clear();
+    }
+  }
+
+  /// Chooses how values of `T` are stored, `StorageKind::Table` unless set
+  /// otherwise. Panics if an entity already has a `T`, so call it before
+  /// adding any.
+  pub fn set_storage<T: Component>(&mut self, kind: StorageKind) {
+    let ty = TypeId::of::<T>();
+    let used = match self.components.get(&ty) {
+      Some(set) => set.len() > 0,
+      None => self.archetypes.is_used(ty),
+    };
+    if used {
+      panic!("storage of `{}` cannot change once an entity has it", type_name::<T>());
+    }
+    match kind {
+      StorageKind::Table => {
+        self.components.remove(&ty);
+        self.archetypes.set_table(ty, type_name::<T>(), Some(storage::new_column::<T>));
+      }
+      StorageKind::SparseSet => {
+        self.archetypes.set_table(ty, type_name::<T>(), None);
+        self.components.insert(ty, SparseSet::new(storage::new_column::<T>()));
+      }
+    }
+  }
+
+  /// Runs `hook` right after `T` is added to an entity that did not have it.
+  /// Registering another hook for `T` replaces this one.
+  pub fn on_add<T: Component>(&mut self, hook: impl Fn(&mut World, EntityId) + MaybeSync + 'static) {
+    self.on_add.insert(TypeId::of::<T>(), Shared::new(hook));
+  }
+
+  /// Runs `hook` right before `T` is removed from an entity, either through
+  /// `remove_component` or `despawn`, while the component can still be read.
+  /// Registering another hook for `T` replaces this one.
+  pub fn on_remove<T: Component>(&mut self, hook: impl Fn(&mut World, EntityId) + MaybeSync + 'static) {
+    self.on_remove.insert(TypeId::of::<T>(), Shared::new(hook));
+  }
+
+  /// Only one resource per type is kept: inserting a resource whose type is
+  /// already present replaces the previous value.
+  pub fn insert_resource<R: Resource>(&mut self, resource: R) {
+    self.resources.insert(TypeId::of::<R>(), Box::new(resource));
+    self.resource_names.insert(TypeId::of::<R>(), type_name::<R>());
+  }
+
+  pub(crate) fn take_resource<R: Resource>(&mut self) -> Option<Box<R>> {
+    self.resources.remove(&TypeId::of::<R>())?.downcast::<R>().ok()
+  }
+
+  pub fn get_resource<R: Resource>(&self) -> Option<&R> {
+    self.resources.get(&TypeId::of::<R>())?.downcast_ref::<R>()
+  }
+
+  pub fn get_resource_mut<R: Resource>(&mut self) -> Option<&mut R> {
+    self.resources.get_mut(&TypeId::of::<R>())?.downcast_mut::<R>()
+  }
+
+  /// Panics if no resource of type `R` was inserted.
+  pub fn resource<R: Resource>(&self) -> &R {
+    self.get_resource::<R>().unwrap_or_else(|| {
+      panic!("resource `{}` does not exist", type_name::<R>())
+    })
+  }
+
+  /// Panics if no resource of type `R` was inserted.
+  pub fn resource_mut<R: Resource>(&mut self) -> &mut R {
+    self.get_resource_mut::<R>().unwrap_or_else(|| {
+      panic!("resource `{}` does not exist", type_name::<R>())
+    })
+  }
+
+  pub fn send_event<T: Resource>(&mut self, event: T) {
+    if self.get_resource::<Events<T>>().is_none() {
+      self.insert_resource(Events::<T>::new());
+    }
+    self.resource_mut::<Events<T>>().send(event);
+  }
+
+  pub fn read_events<T: Resource>(&self) -> impl Iterator<Item = &T> {
+    self.get_resource::<Events<T>>()
+      .into_iter()
+      .flat_map(|events| events.iter())
+  }
+
+  /// See `Events::update`.
+  pub fn update_events<T: Resource>(&mut self) {
+    if let Some(events) = self.get_resource_mut::<Events<T>>() {
+      events.update();
+    }
+  }
+
+  pub fn clear_events<T: Resource>(&mut self) {
+    if let Some(events) = self.get_resource_mut::<Events<T>>() {
+      events.clear();
+    }
+  }
+
+  pub fn run_system<M>(&mut self, system: impl IntoSystem<M>) {
+    system.into_system().run(self);
+  }
+
+  pub fn apply_commands(&mut self, commands: Commands) {
+    self.deferring_observers(|world| world.run_commands(commands));
+  }
+
+  pub(crate) fn run_commands(&mut self, commands: Commands) {
+    for command in commands.queue {
+      match command {
+        Command::Spawn(insert) => {
+          let id = self.spawn();
+          insert(self, id);
+        }
+        Command::Despawn(id) => {
+          self.despawn(id);
+        }
+        Command::AddComponent(id, insert) => insert(self, id),
+        Command::RemoveComponent(id, ty) => {
+          self.remove_boxed(id, ty);
+        }
+        Command::Update(update) => update(self),
+      }
+    }
+  }
+}
+
+impl Default for World {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Spawns an entity per bundle, like `spawn_batch`.
+impl<B: Bundle> FromIterator<B> for World {
+  fn from_iter<I: IntoIterator<Item = B>>(bundles: I) -> Self {
+    let mut world = World::new();
+    world.extend(bundles);
+    world
+  }
+}
+
+impl<B: Bundle> Extend<B> for World {
+  fn extend<I: IntoIterator<Item = B>>(&mut self, bundles: I) {
+    self.spawn_batch(bundles);
+  }
+}
+
+/// What the macros and derives expand to, for crates without `std`.
+#[doc(hidden)]
+pub mod __private {
+  pub use alloc::boxed::Box;
+  pub use alloc::vec;
+  pub use alloc::vec::Vec;
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __query {
+  // Splits the listed components into required ones, `?`-prefixed optional
+  // ones, `any(...)` groups and `Tag<...>`, `Changed<...>` or `Added<...>`
+  // filters, keeping their order for the yielded tuple.
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; ? $comp:ty , $($rest:tt)*) => {
+    $crate::__query!(@items $wrap; $world; [$($req,)*] [$($item)* (optional $comp)] [$($any)*] [$($filter)*]; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; ? $comp:ty ; $($rest:tt)*) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)*] [$($item)* (optional $comp)] [$($any)*] [$($filter)*] ; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; ? $comp:ty) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)*] [$($item)* (optional $comp)] [$($any)*] [$($filter)*])
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; any($($alt:ty),+ $(,)?) , $($rest:tt)*) => {
+    $crate::__query!(@items $wrap; $world; [$($req,)*] [$($item)*] [$($any)* ($($alt),+)] [$($filter)*]; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; any($($alt:ty),+ $(,)?) ; $($rest:tt)*) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)*] [$($item)*] [$($any)* ($($alt),+)] [$($filter)*] ; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; any($($alt:ty),+ $(,)?)) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)*] [$($item)*] [$($any)* ($($alt),+)] [$($filter)*])
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; Tag<$t:ty> , $($rest:tt)*) => {
+    $crate::__query!(@items $wrap; $world; [$($req,)*] [$($item)*] [$($any)*] [$($filter)* (tag $t)]; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; Tag<$t:ty> ; $($rest:tt)*) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)*] [$($item)*] [$($any)*] [$($filter)* (tag $t)] ; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; Tag<$t:ty>) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)*] [$($item)*] [$($any)*] [$($filter)* (tag $t)])
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; Changed<$t:ty> , $($rest:tt)*) => {
+    $crate::__query!(@items $wrap; $world; [$($req,)* $t,] [$($item)*] [$($any)*] [$($filter)* (changed $t)]; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; Changed<$t:ty> ; $($rest:tt)*) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)* $t,] [$($item)*] [$($any)*] [$($filter)* (changed $t)] ; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; Changed<$t:ty>) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)* $t,] [$($item)*] [$($any)*] [$($filter)* (changed $t)])
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; Added<$t:ty> , $($rest:tt)*) => {
+    $crate::__query!(@items $wrap; $world; [$($req,)* $t,] [$($item)*] [$($any)*] [$($filter)* (added $t)]; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; Added<$t:ty> ; $($rest:tt)*) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)* $t,] [$($item)*] [$($any)*] [$($filter)* (added $t)] ; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; Added<$t:ty>) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)* $t,] [$($item)*] [$($any)*] [$($filter)* (added $t)])
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; $comp:ty , $($rest:tt)*) => {
+    $crate::__query!(@items $wrap; $world; [$($req,)* $comp,] [$($item)* (required $comp)] [$($any)*] [$($filter)*]; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; $comp:ty ; $($rest:tt)*) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)* $comp,] [$($item)* (required $comp)] [$($any)*] [$($filter)*] ; $($rest)*)
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*]; $comp:ty) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)* $comp,] [$($item)* (required $comp)] [$($any)*] [$($filter)*])
+  };
+  (@items $wrap:path; $world:ident; [$($req:ty,)*] [$($item:tt)*] [$($any:tt)*] [$($filter:tt)*];) => {
+    $crate::__query!(@done $wrap; $world; [$($req,)*] [$($item)*] [$($any)*] [$($filter)*])
+  };
+  (@item required $wrap:path; $world:ident; $entity:ident; $comp:ty) => {
+    $wrap($world.get::<$comp>($entity)?)
+  };
+  (@item optional $wrap:path; $world:ident; $entity:ident; $comp:ty) => {
+    $world.get::<$comp>($entity).map($wrap)
+  };
+  (@filter $world:ident; $entity:ident; (tag $t:ty)) => {
+    $world.has_tag::<$t>($entity)
+  };
+  (@filter $world:ident; $entity:ident; (changed $t:ty)) => {
+    $world.is_changed::<$t>($entity)
+  };
+  (@filter $world:ident; $entity:ident; (added $t:ty)) => {
+    $world.is_added::<$t>($entity)
+  };
+  (
+    @done $wrap:path; $world:ident; [$($req:ty,)*] [$(($kind:ident $comp:ty))*] [$(($($alt:ty),+))*] [$($filter:tt)*]
+    $(; with $($incl:ty),+)?
+    $(; without $($excl:ty),+)?
+    $(; changed $($chg:ty),+)?
+    $(; added $($add:ty),+)?
+    $(; by $compare:expr)?
+    $(; by_key $key:expr)?
+    $(,)?
+  ) => {{
+    let items = $world.matching_entities($crate::__private::vec![
+      $(::core::any::TypeId::of::<$req>(),)*
+      $($(::core::any::TypeId::of::<$incl>(),)+)?
+    ])
+    .filter_map(|entity| {
+      if false
+        $($(|| $world.get::<$excl>(entity).is_some())+)?
+        $($(|| !$world.is_changed::<$chg>(entity))+)?
+        $($(|| !$world.is_added::<$add>(entity))+)?
+        $(|| !(false $(|| $world.get::<$alt>(entity).is_some())+))*
+        $(|| !$crate::__query!(@filter $world; entity; $filter))*
+      {
+        return None;
+      }
+      Some((
+        entity,
+        $($crate::__query!(@item $kind $wrap; $world; entity; $comp),)*
+      ))
+    });
+    $(
+      let items: $crate::__private::Vec<_> = items.collect();
+      let order = $crate::__order_by(&items, $compare);
+      let items = $crate::__permute(items, order);
+    )?
+    $(
+      let items: $crate::__private::Vec<_> = items.collect();
+      let order = $crate::__order_by_key(&items, $key);
+      let items = $crate::__permute(items, order);
+    )?
+    items
+  }};
+  ($wrap:path; $world:ident , $($rest:tt)+) => {
+    $crate::__query!(@items $wrap; $world; [] [] [] []; $($rest)+)
+  };
+}
+
+/// Yields `(EntityId, &A, &B, ...)` for every entity that has all of the
+/// listed components. A component prefixed with `?` does not have to be
+/// present and is yielded as an `Option`. An `any(A, B)` group requires at
+/// least one of its components but yields none of them, and `Tag<T>`
+/// requires the entity to be tagged with `T` through `World::tag`.
+/// `Changed<T>` and `Added<T>` require a `T` that was changed or added since
+/// the last `World::clear_trackers` without yielding it, the same as a
+/// trailing `; changed T` or `; added T` clause.
+///
+/// A trailing `; by |a, b| ...` or `; by_key |a| ...` clause sorts the items,
+/// with the closure given the components alone: the component itself when
+/// one is listed, or a tuple of them otherwise.
+#[macro_export]
+macro_rules! query {
+  ( $($args:tt)* ) => {
+    $crate::__query!(::core::convert::identity; $($args)*)
+  };
+}
+
+/// Another name for `query!`, for queries with `?`-prefixed optional
+/// components: `query_optional!(world, Position, ?Name)` yields
+/// `(EntityId, &Position, Option<&Name>)` for every entity with a `Position`.
+#[macro_export]
+macro_rules! query_optional {
+  ( $($args:tt)* ) => {
+    $crate::query!($($args)*)
+  };
+}
+
+/// Like `query!`, but only yields entities whose listed components were all
+/// changed since the last `World::clear_trackers`, or at or after a tick
+/// obtained from `World::change_tick` when `; since tick` is given.
+#[macro_export]
+macro_rules! query_changed {
+  ( $world:ident , $($comp:ty),+ $(,)? ) => {
+    $crate::query!($world, $($comp),+; changed $($comp),+)
+  };
+  ( $world:ident , $($comp:ty),+ ; since $tick:expr ) => {
+    $crate::query!($world, $($comp),+)
+      .filter(|item| true $(&& $world.is_changed_since::<$comp>(item.0, $tick))+)
+  };
+}
+
+/// The number of entities `query!` would yield for the same arguments. Only
+/// references are taken along the way; no component is cloned.
+#[macro_export]
+macro_rules! query_count {
+  ( $($args:tt)* ) => {
+    $crate::query!($($args)*).count()
+  };
+}
+
+/// The ids of the entities `query!` would yield for the same arguments, in
+/// the same order, e.g. to split them into chunks for threads of one's own.
+#[macro_export]
+macro_rules! collect_ids {
+  ( $($args:tt)* ) => {
+    $crate::query!($($args)*).map(|item| item.0).collect::<$crate::__private::Vec<$crate::EntityId>>()
+  };
+}
+
+/// Yields `(EntityId, &T, Option<&T>)` for every entity with a `T`, along
+/// with the value it had at the last `clear_trackers`. `T` has to be tracked
+/// with `World::track_previous`; entities that got their `T` since have no
+/// previous value.
+#[macro_export]
+macro_rules! query_prev {
+  ( $world:ident , $comp:ty $(,)? ) => {
+    $crate::query!($world, $comp).map(|(id, current)| (id, current, $world.previous::<$comp>(id)))
+  };
+}
+
+/// Like `query!`, but returns the only matching item, or `None` if nothing
+/// matches. Panics if more than one entity matches, since that usually means
+/// the world is not set up as expected; use `try_query_one!` to handle it.
+#[macro_export]
+macro_rules! query_one {
+  ( $($args:tt)* ) => {
+    match $crate::try_query_one!($($args)*) {
+      ::core::result::Result::Ok(item) => ::core::option::Option::Some(item),
+      ::core::result::Result::Err($crate::QuerySingleError::NoEntities(_)) => {
+        ::core::option::Option::None
+      }
+      ::core::result::Result::Err(error) => panic!("{}", error),
+    }
+  };
+}
+
+/// Like `query_one!`, but returns a `QuerySingleError` instead of panicking.
+#[macro_export]
+macro_rules! try_query_one {
+  ( $world:ident , $($args:tt)* ) => {
+    $crate::__single($crate::query!($world, $($args)*), stringify!($($args)*))
+  };
+}
+
+/// Like `query!`, but clones each component out of the world.
+#[macro_export]
+macro_rules! query_cloned {
+  ( $($args:tt)* ) => {
+    $crate::__query!(::core::clone::Clone::clone; $($args)*)
+  };
+}
+
+#[macro_export]
+macro_rules! query_mut {
+  ( $world:ident , $($comp:ty),+ $(,)? ) => {{
+    let (entities, mut storages) = $world.storages_mut([
+      $((::core::any::TypeId::of::<$comp>(), ::core::any::type_name::<$comp>()),)+
+    ]);
+    entities.filter_map(move |entity| {
+      let mut components = storages.fetch(entity)?.into_iter();
+      Some((
+        entity,
+        $(components.next()?.downcast_mut::<$comp>()?,)+
+      ))
+    })
+  }}
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Position(i32, i32);
+
+  #[test]
+  fn despawn_removes_the_entity_from_queries() {
+    let mut world = World::new();
+    let a = world.spawn_bundle((Position(0, 0),));
+    let b = world.spawn_bundle((Position(1, 1),));
+    let c = world.spawn_bundle((Position(2, 2),));
+
+    assert!(world.despawn(b));
+    assert_eq!(world.len(), 2);
+    let ids: Vec<EntityId> = query!(world, Position).map(|(id, _)| id).collect();
+    assert_eq!(ids, vec![a, c]);
+    assert!(!world.despawn(b));
+    assert_eq!(world.len(), 2);
+  }
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Velocity(i32, i32);
+
+  #[test]
+  fn query_mut_writes_back_into_the_world() {
+    let mut world = World::new();
+    let moving = world.spawn_bundle((Position(0, 0), Velocity(1, 2)));
+    let still = world.spawn_bundle((Position(5, 5),));
+
+    for (_, pos, vel) in query_mut!(world, Position, Velocity) {
+      pos.0 += vel.0;
+      pos.1 += vel.1;
+    }
+
+    assert_eq!(world.get::<Position>(moving), Some(&Position(1, 2)));
+    assert_eq!(world.get::<Position>(still), Some(&Position(5, 5)));
+  }
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Label(&'static str);
+
+  #[test]
+  fn removed_components_leave_queries_for_them() {
+    let mut world = World::new();
+    let player = world.spawn_bundle((Position(0, 0), Label("player")));
+
+    assert_eq!(world.remove_component::<Label>(player), Some(Label("player")));
+    assert_eq!(world.remove_component::<Label>(player), None);
+    assert_eq!(query!(world, Label).count(), 0);
+    let ids: Vec<EntityId> = query!(world, Position).map(|(id, _)| id).collect();
+    assert_eq!(ids, vec![player]);
+  }
+
+  #[test]
+  fn without_excludes_entities_that_have_the_component() {
+    let mut world = World::new();
+    let point = world.spawn_bundle((Position(1, 1),));
+    world.spawn_bundle((Position(0, 0), Label("player")));
+
+    let ids: Vec<EntityId> = query!(world, Position; without Label).map(|(id, _)| id).collect();
+    assert_eq!(ids, vec![point]);
+  }
+
+  #[test]
+  fn get_and_get_mut_report_despawned_entities_missing() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Position(0, 0),));
+
+    assert_eq!(world.get::<Position>(id), Some(&Position(0, 0)));
+    world.get_mut::<Position>(id).unwrap().0 = 3;
+    assert_eq!(world.get::<Position>(id), Some(&Position(3, 0)));
+    assert_eq!(world.get::<Label>(id), None);
+
+    world.despawn(id);
+    assert_eq!(world.get::<Position>(id), None);
+    assert!(world.get_mut::<Position>(id).is_none());
+  }
+
+  #[test]
+  fn stale_ids_do_not_resolve_after_their_slot_is_reused() {
+    let mut world = World::new();
+    let old = world.spawn_bundle((Position(0, 0),));
+    world.despawn(old);
+    let new = world.spawn_bundle((Position(1, 1),));
+
+    assert_eq!(new.index(), old.index());
+    assert_eq!(new.generation(), old.generation() + 1);
+    assert!(!world.is_alive(old));
+    assert_eq!(world.get::<Position>(old), None);
+    assert_eq!(world.get::<Position>(new), Some(&Position(1, 1)));
+  }
+
+  #[test]
+  fn len_and_iter_entities_follow_spawn_order() {
+    let mut world = World::new();
+    assert!(world.is_empty());
+    let ids = [world.spawn(), world.spawn(), world.spawn()];
+
+    assert_eq!(world.len(), 3);
+    assert!(!world.is_empty());
+    let walked: Vec<EntityId> = world.iter_entities().map(|entity| entity.id()).collect();
+    assert_eq!(walked, ids);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn entity_ids_round_trip_through_serde() {
+    let mut world = World::new();
+    let id = world.spawn();
+    world.despawn(id);
+    let id = world.spawn();
+
+    let json = serde_json::to_string(&id).unwrap();
+    assert_eq!(serde_json::from_str::<EntityId>(&json).unwrap(), id);
+  }
+
+  #[test]
+  fn spawned_bundles_are_queryable_by_each_component() {
+    let mut world = World::new();
+    let one = world.spawn_bundle((Position(0, 0),));
+    let two = world.spawn_bundle((Position(1, 1), Velocity(1, 0)));
+    let three = world.spawn_bundle((Position(2, 2), Velocity(0, 1), Label("three")));
+
+    let ids: Vec<EntityId> = query!(world, Position).map(|(id, _)| id).collect();
+    assert_eq!(ids, vec![one, two, three]);
+    let ids: Vec<EntityId> = query!(world, Velocity).map(|(id, _)| id).collect();
+    assert_eq!(ids, vec![two, three]);
+    let ids: Vec<EntityId> = query!(world, Label).map(|(id, _)| id).collect();
+    assert_eq!(ids, vec![three]);
+    assert_eq!(world.get::<Velocity>(three), Some(&Velocity(0, 1)));
+  }
+
+  #[test]
+  fn query_changed_yields_only_mutated_components() {
+    let mut world = World::new();
+    let ids = world.spawn_batch([(Position(0, 0),), (Position(1, 1),), (Position(2, 2),)]);
+    assert_eq!(query_changed!(world, Position).count(), 3);
+
+    world.clear_trackers();
+    assert_eq!(query_changed!(world, Position).count(), 0);
+    let tick = world.change_tick();
+    world.get_mut::<Position>(ids[1]).unwrap().0 += 1;
+
+    let changed: Vec<EntityId> = query_changed!(world, Position).map(|(id, _)| id).collect();
+    assert_eq!(changed, vec![ids[1]]);
+    world.clear_trackers();
+    assert_eq!(query_changed!(world, Position).count(), 0);
+    let since: Vec<EntityId> = query_changed!(world, Position; since tick).map(|(id, _)| id).collect();
+    assert_eq!(since, vec![ids[1]]);
+  }
+
+  #[test]
+  fn spawn_returns_an_id_that_resolves() {
+    let mut world = World::new();
+    let player = world.spawn_bundle((Position(4, 2),));
+    let empty = world.spawn();
+
+    assert_eq!(world.get::<Position>(player), Some(&Position(4, 2)));
+    assert!(world.is_alive(empty));
+    assert_ne!(player, empty);
+  }
+
+  #[test]
+  fn optional_components_are_yielded_as_options() {
+    let mut world = World::new();
+    let point = world.spawn_bundle((Position(1, 1),));
+    let player = world.spawn_bundle((Position(0, 0), Label("player")));
+
+    let items: Vec<_> = query!(world, Position, ?Label).collect();
+    assert_eq!(items, vec![
+      (point, &Position(1, 1), None),
+      (player, &Position(0, 0), Some(&Label("player"))),
+    ]);
+  }
+
+  #[test]
+  fn spawn_batch_returns_every_new_id() {
+    let mut world = World::new();
+    let ids = world.spawn_batch((0..100).map(|i| (Position(i, 0),)));
+
+    assert_eq!(ids.len(), 100);
+    assert_eq!(world.entities(), &ids[..]);
+    assert!(world.capacity() >= 100);
+    for (i, &id) in ids.iter().enumerate() {
+      assert_eq!(world.get::<Position>(id), Some(&Position(i as i32, 0)));
+    }
+  }
+
+  #[test]
+  fn clearing_keeps_the_entity_capacity() {
+    let mut world = World::new();
+    world.spawn_batch((0..64).map(|i| (Position(i, i),)));
+    let capacity = world.capacity();
+
+    world.clear_entities();
+    assert_eq!(world.len(), 0);
+    assert_eq!(query!(world, Position).count(), 0);
+    assert_eq!(world.capacity(), capacity);
+
+    world.spawn_bundle((Position(0, 0),));
+    world.clear();
+    assert_eq!(world.len(), 0);
+    assert_eq!(query!(world, Position).count(), 0);
+    assert_eq!(world.capacity(), capacity);
+  }
+
+  #[test]
+  fn batches_written_into_columns_behave_like_single_spawns() {
+    let mut world = World::new();
+    let before = world.spawn_bundle((Position(-1, -1), Velocity(0, 0)));
+    let ids = world.spawn_batch((0..3).map(|i| (Position(i, i), Velocity(i, 0))));
+
+    let archetype = world.archetypes().iter().find(|archetype| archetype.entities().contains(&before)).unwrap();
+    assert_eq!(archetype.entities(), &[before, ids[0], ids[1], ids[2]]);
+    assert!(ids.iter().all(|&id| world.is_added::<Position>(id) && world.is_changed::<Velocity>(id)));
+    world.remove_component::<Velocity>(ids[0]);
+    world.despawn(before);
+    let items: Vec<_> = query!(world, Position, Velocity).collect();
+    assert_eq!(items, vec![
+      (ids[1], &Position(1, 1), &Velocity(1, 0)),
+      (ids[2], &Position(2, 2), &Velocity(2, 0)),
+    ]);
+    assert_eq!(world.get::<Position>(ids[0]), Some(&Position(0, 0)));
+  }
+
+  #[test]
+  fn batches_still_run_on_add_hooks() {
+    let mut world = World::new();
+    world.on_add::<Velocity>(|world, id| world.add_component(id, Label("moving")));
+    let ids = world.spawn_batch([(Position(0, 0), Velocity(1, 0)), (Position(1, 1), Velocity(0, 1))]);
+
+    assert!(ids.iter().all(|&id| world.get::<Label>(id) == Some(&Label("moving"))));
+  }
+
+  #[test]
+  fn contains_only_live_ids() {
+    let mut world = World::new();
+    let live = world.spawn();
+    let gone = world.spawn();
+    world.despawn(gone);
+
+    assert!(world.contains(live));
+    assert!(!world.contains(gone));
+    assert!(!world.contains(EntityId::from_raw(0xdead_beef_0000_0042)));
+  }
+
+  #[test]
+  fn with_capacity_reserves_room_for_entities() {
+    let mut world = World::with_capacity(256);
+    let capacity = world.capacity();
+    assert!(capacity >= 256);
+
+    world.spawn_batch((0..256).map(|i| (Position(i, 0),)));
+    assert_eq!(world.capacity(), capacity);
+    assert!(World::new().capacity() < 256);
+  }
+
+  #[test]
+  fn retain_despawns_the_entities_it_rejects() {
+    let mut world = World::new();
+    let kept: Vec<EntityId> = (0..4).map(|i| world.spawn_bundle((Position(i, 0),))).collect();
+    let named = world.spawn_bundle((Name::new("label only"),));
+    world.retain(|id, world| world.has_component::<Position>(id));
+
+    assert_eq!(world.len(), 4);
+    assert!(!world.contains(named));
+    assert_eq!(world.find_by_name("label only"), None);
+    let ids: Vec<EntityId> = query!(world, Position).map(|(id, _)| id).collect();
+    assert_eq!(ids, kept);
+  }
+
+  #[derive(Debug, PartialEq)]
+  struct Player;
+
+  #[test]
+  fn unit_structs_filter_queries_as_tags() {
+    let mut world = World::new();
+    let player = world.spawn_bundle((Position(0, 0), Player));
+    world.spawn_bundle((Position(1, 1),));
+    let ids: Vec<EntityId> = query!(world, Position; with Player).map(|(id, _)| id).collect();
+
+    assert_eq!(ids, [player]);
+    assert_eq!(world.get::<Player>(player), Some(&Player));
+  }
+
+  #[test]
+  fn queries_yield_entities_in_spawn_order() {
+    let mut world = World::new();
+    world.set_storage::<Label>(StorageKind::SparseSet);
+    let ids: Vec<EntityId> = (0..8)
+      .map(|i| match i % 3 {
+        0 => world.spawn_bundle((Position(i, 0),)),
+        1 => world.spawn_bundle((Position(i, 0), Velocity(0, 0))),
+        _ => world.spawn_bundle((Position(i, 0), Label("sparse"))),
+      })
+      .collect();
+    // Shuffles the rows of both archetypes and the sparse set.
+    world.remove_component::<Velocity>(ids[1]);
+    world.add_component(ids[1], Velocity(0, 0));
+    world.remove_component::<Label>(ids[2]);
+    world.add_component(ids[2], Label("sparse"));
+
+    let first: Vec<EntityId> = query!(world, Position).map(|(id, _)| id).collect();
+    let second: Vec<EntityId> = query!(world, Position).map(|(id, _)| id).collect();
+    assert_eq!(first, second);
+    assert_eq!(first, world.entities());
+    let mutated: Vec<EntityId> = query_mut!(world, Position).map(|(id, _)| id).collect();
+    assert_eq!(mutated, world.entities());
+    let labelled: Vec<EntityId> = query_mut!(world, Position, Label).map(|(id, _, _)| id).collect();
+    assert_eq!(labelled, [ids[2], ids[5]]);
+  }
+
+  #[test]
+  fn worlds_collect_and_extend_from_bundles() {
+    let bundles = vec![(Position(0, 0), Velocity(1, 0)), (Position(1, 1), Velocity(0, 1))];
+    let mut world: World = bundles.into_iter().collect();
+    world.extend([(Position(2, 2),)]);
+
+    assert_eq!(world.len(), 3);
+    let moving: Vec<(i32, i32)> = query!(world, Position, Velocity).map(|(_, pos, vel)| (pos.0, vel.1)).collect();
+    assert_eq!(moving, [(0, 0), (1, 1)]);
+    assert_eq!(query_count!(world, Position), 3);
+  }
+
+  #[test]
+  fn hooks_fire_once_per_add_and_remove() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let mut world = World::new();
+    let added = Arc::new(AtomicUsize::new(0));
+    let removed = Arc::new(AtomicUsize::new(0));
+    let counter = added.clone();
+    world.on_add::<Position>(move |_, _| {
+      counter.fetch_add(1, Ordering::SeqCst);
+    });
+    let counter = removed.clone();
+    world.on_remove::<Position>(move |world, id| {
+      assert!(world.get::<Position>(id).is_some());
+      counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let a = world.spawn_bundle((Position(0, 0), Velocity(0, 0)));
+    let b = world.spawn_bundle((Position(1, 1),));
+    world.add_component(a, Position(2, 2));
+    world.update::<Position>(a, |pos| pos.0 += 1);
+    assert_eq!((added.load(Ordering::SeqCst), removed.load(Ordering::SeqCst)), (2, 0));
+
+    world.remove_component::<Position>(b);
+    world.despawn(a);
+    world.despawn(b);
+    assert_eq!((added.load(Ordering::SeqCst), removed.load(Ordering::SeqCst)), (2, 2));
+  }
+
+  #[test]
+  fn try_spawn_fails_at_the_entity_limit() {
+    let mut world = World::new();
+    world.set_entity_limit(2);
+    assert!(world.try_spawn((Position(0, 0),)).is_ok());
+    let second = world.try_spawn((Position(1, 1),)).unwrap();
+
+    assert_eq!(world.try_spawn((Position(2, 2),)), Err(SpawnError::AtCapacity(2)));
+    assert_eq!(world.len(), 2);
+    world.despawn(second);
+    assert!(world.try_spawn((Position(2, 2),)).is_ok());
+  }
+
+  #[test]
+  #[should_panic(expected = "the world is at its limit of 1 entities")]
+  fn spawn_panics_at_the_entity_limit() {
+    let mut world = World::new();
+    world.set_entity_limit(1);
+    world.spawn();
+    world.spawn();
+  }
+
+  #[test]
+  fn entity_ids_round_trip_through_display_and_raw() {
+    let mut world = World::new();
+    let gone = world.spawn();
+    world.despawn(gone);
+    let id = world.spawn();
+
+    assert_eq!(id.to_string(), "0v1");
+    assert_eq!(id.to_string().parse::<EntityId>(), Ok(id));
+    assert_eq!(EntityId::from_raw(id.raw()), id);
+    assert_eq!("0v".parse::<EntityId>(), Err(ParseEntityIdError));
+    assert_eq!("7".parse::<EntityId>(), Err(ParseEntityIdError));
+  }
+
+  #[test]
+  fn query_optional_yields_every_entity_with_the_required_components() {
+    let mut world = World::new();
+    let named = world.spawn_bundle((Position(0, 0), Name::new("Ian")));
+    let anonymous = world.spawn_bundle((Position(1, 1),));
+    world.spawn_bundle((Name::new("no position"),));
+
+    let items: Vec<(EntityId, Position, Option<&str>)> = query_optional!(world, Position, ?Name)
+      .map(|(id, pos, name)| (id, pos.clone(), name.map(|name| name.as_str())))
+      .collect();
+    assert_eq!(items, [(named, Position(0, 0), Some("Ian")), (anonymous, Position(1, 1), None)]);
+  }
+
+  #[test]
+  fn any_groups_match_entities_with_either_alternative() {
+    let mut world = World::new();
+    let named = world.spawn_bundle((Position(0, 0), Name::new("Ian")));
+    let player = world.spawn_bundle((Position(1, 1), Player));
+    let both = world.spawn_bundle((Position(2, 2), Name::new("Ada"), Player));
+    world.spawn_bundle((Position(3, 3),));
+    world.spawn_bundle((Name::new("no position"), Player));
+
+    let ids: Vec<EntityId> = query!(world, Position, any(Name, Player)).map(|(id, _)| id).collect();
+    assert_eq!(ids, [named, player, both]);
+    let named_players: Vec<EntityId> =
+      query!(world, any(Velocity, Player), Position; with Name).map(|(id, _)| id).collect();
+    assert_eq!(named_players, [both]);
+  }
+
+  #[test]
+  fn query_raw_yields_components_in_the_requested_order() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Position(1, 2), Label("a")));
+    world.spawn_bundle((Position(3, 4),));
+
+    let types = [TypeId::of::<Label>(), TypeId::of::<Position>()];
+    let items: Vec<(EntityId, Vec<&dyn Any>)> = world.query_raw(&types).collect();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].0, id);
+    assert_eq!(items[0].1[0].downcast_ref::<Label>(), Some(&Label("a")));
+    assert_eq!(items[0].1[1].downcast_ref::<Position>(), Some(&Position(1, 2)));
+  }
+
+  #[test]
+  fn collect_ids_lists_what_query_yields() {
+    let mut world = World::new();
+    for i in 0..10 {
+      let id = world.spawn_bundle((Position(i, 0),));
+      if i % 3 == 0 {
+        world.add_component(id, Name::new(format!("n{}", i)));
+      }
+    }
+
+    let ids = collect_ids!(world, Position, Name);
+    let queried: Vec<EntityId> = query!(world, Position, Name).map(|(id, ..)| id).collect();
+    assert_eq!(ids, queried);
+    assert_eq!(ids.len(), 4);
+  }
+
+  #[test]
+  fn despawn_where_counts_what_it_despawned() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let mut world = World::new();
+    let removed = Arc::new(AtomicUsize::new(0));
+    let counter = removed.clone();
+    world.on_remove::<Position>(move |_, _| {
+      counter.fetch_add(1, Ordering::SeqCst);
+    });
+    let origin = world.spawn_bundle((Position(0, 0),));
+    let near = world.spawn_bundle((Position(1, 0),));
+    for i in 5..8 {
+      world.spawn_bundle((Position(i, 0),));
+    }
+
+    let far = |id, world: &World| {
+      let origin = world.get::<Position>(origin).expect("the origin is kept");
+      world.get::<Position>(id).is_some_and(|pos| pos.0 - origin.0 > 2)
+    };
+    assert_eq!(world.despawn_where(far), 3);
+    assert_eq!(removed.load(Ordering::SeqCst), 3);
+    let ids: Vec<EntityId> = query!(world, Position).map(|(id, _)| id).collect();
+    assert_eq!(ids, [origin, near]);
+    assert_eq!(world.despawn_where(far), 0);
+  }
+
+  #[test]
+  fn changed_filters_reset_with_clear_trackers() {
+    let mut world = World::new();
+    let a = world.spawn_bundle((Position(0, 0), Velocity(1, 0)));
+    let b = world.spawn_bundle((Position(1, 1),));
+    let ids: Vec<EntityId> = query!(world, Changed<Position>).map(|(id,)| id).collect();
+    assert_eq!(ids, [a, b]);
+
+    world.clear_trackers();
+    assert_eq!(query_count!(world, Changed<Position>), 0);
+    world.get_mut::<Position>(b).unwrap().0 += 1;
+    let moved: Vec<(EntityId, &Velocity)> = query!(world, Velocity, Changed<Position>).collect();
+    assert!(moved.is_empty());
+    let ids: Vec<EntityId> = query!(world, Changed<Position>).map(|(id,)| id).collect();
+    assert_eq!(ids, [b]);
+    assert_eq!(ids, query!(world, Position; changed Position).map(|(id, _)| id).collect::<Vec<_>>());
+
+    world.clear_trackers();
+    assert_eq!(query_count!(world, Changed<Position>), 0);
+  }
+
+  #[test]
+  fn added_filters_reset_with_clear_trackers() {
+    let mut world = World::new();
+    let a = world.spawn_bundle((Name::new("a"),));
+    world.clear_trackers();
+    let b = world.spawn_bundle((Name::new("b"),));
+    world.get_mut::<Name>(a).unwrap();
+
+    let ids: Vec<EntityId> = query!(world, Added<Name>).map(|(id,)| id).collect();
+    assert_eq!(ids, [b]);
+    assert_eq!(query_count!(world, Changed<Name>), 2);
+    assert_eq!(ids, query!(world, Name; added Name).map(|(id, _)| id).collect::<Vec<_>>());
+
+    world.clear_trackers();
+    assert_eq!(query_count!(world, Added<Name>), 0);
+    world.add_component(a, Position(0, 0));
+    let ids: Vec<EntityId> = query!(world, Name, Added<Position>).map(|(id, _)| id).collect();
+    assert_eq!(ids, [a]);
+  }
+}
@@ -1,137 +1,157 @@
-use uuid::Uuid;
-use std::collections::HashMap;
+use tecs::{
+  query, Bundle, Commands, Components, DefaultPlugins, EntityId, Events, FrameCount, Query, Res, ResMut,
+  Schedule, World,
+};
 
-struct Entity<C> {
-  id: Uuid,
-  component: C,
-}
-
-impl<C> Entity<C> {
-  fn new(component: C) -> Self {
-    Self {
-      id: Uuid::new_v4(),
-      component,
-    }
-  }
-}
+#[derive(Debug)]
+struct Position(i32, i32);
 
-struct World<C> {
-  entities: Vec<Uuid>,
-  components: HashMap<Uuid, C>,
-}
+#[derive(Debug)]
+struct Velocity(i32, i32);
 
-impl<C> World<C> {
-  fn new() -> Self {
-    Self {
-      entities: vec![],
-      components: HashMap::new(),
-    }
-  }
+#[derive(Debug)]
+struct Name(String);
 
-  fn spawn(&mut self, entity: Entity<C>) {
-    self.entities.push(entity.id);
-    self.components.insert(entity.id, entity.component);
-  }
-}
+#[derive(Debug)]
+struct Frozen(u32);
 
-// Usage
+struct Thawed(EntityId);
 
-#[derive(Debug, Clone)]
-struct Position(i32, i32);
+struct Player;
 
-#[derive(Debug, Clone)]
-struct Name(String);
+#[derive(Bundle)]
+struct PlayerBundle {
+  pos: Position,
+  vel: Velocity,
+  name: Name,
+  player: Player,
+}
 
-struct Component {
+#[derive(Components)]
+struct Spawn {
+  pos: Option<Position>,
   name: Option<Name>,
-  position: Option<Position>,
+  frozen: Option<Frozen>,
 }
 
-impl Component {
-  fn new() -> Self {
-    Self {
-      name: None,
-      position: None,
-    }
-  }
+const BOUNDS: i32 = 5;
 
-  fn with_position(self, position: Position) -> Self {
-    Self {
-      position: Some(position),
-      ..self
+fn movement_system(mut query: Query<(&mut Position, &Velocity)>, commands: &mut Commands) {
+  for (id, (pos, vel)) in query.iter() {
+    pos.0 += vel.0;
+    pos.1 += vel.1;
+    println!("(ID: {})", id);
+    println!("[MOVEMENT] {:?}", pos);
+    if pos.0.abs() > BOUNDS || pos.1.abs() > BOUNDS {
+      commands.despawn(id);
     }
   }
+}
 
-  fn with_name(self, name: Name) -> Self {
-    Self {
-      name: Some(name),
-      ..self
-    }
+fn greet_system(world: &World) {
+  let entities = query!(world, Name);
+  for (id, name) in entities {
+    println!("(ID: {})", id);
+    println!("[NAME] {:?}", name);
   }
 }
 
-macro_rules! query {
-  ( $world:ident , $($comp:ident),+ $(,)? ) => {
-    $world.entities.iter().filter_map(|entity| {
-      let comps = $world.components.get(entity)?;
-      Some((
-        entity,
-        $(comps.$comp.clone()?,)+
-      ))
-    })
+fn render_system(world: &World) {
+  let entities = query!(world, Position, Name);
+  for (id, pos, name) in entities {
+    println!("(ID: {})", id);
+    println!("[RENDER] {:?} at {:?}", name, pos);
   }
 }
 
-fn movement_system(world: &World<Component>) {
-  let entities = query!(world, position);
+fn projectile_system(world: &World) {
+  let entities = query!(world, Position; without Name);
   for (id, pos) in entities {
     println!("(ID: {})", id);
-    println!("[MOVEMENT] {:?}", pos);
+    println!("[PROJECTILE] {:?}", pos);
   }
 }
 
-fn greet_system(world: &World<Component>) {
-  let entities = query!(world, name);
-  for (id, name) in entities {
-    println!("(ID: {})", id);
-    println!("[NAME] {:?}", name);
+fn thaw_system(
+  mut query: Query<&mut Frozen>,
+  mut thawed: ResMut<Events<Thawed>>,
+  commands: &mut Commands,
+) {
+  for (id, frozen) in query.iter() {
+    frozen.0 = frozen.0.saturating_sub(1);
+    if frozen.0 == 0 {
+      commands.remove_component::<Frozen>(id);
+      thawed.send(Thawed(id));
+    }
   }
 }
 
-fn render_system(world: &World<Component>) {
-  let entities = query!(world, position, name);
-  for (id, pos, name) in entities {
+fn thaw_log_system(thawed: Res<Events<Thawed>>) {
+  for Thawed(id) in thawed.iter() {
     println!("(ID: {})", id);
-    println!("[RENDER] {:?} at {:?}", name, pos);
+    println!("[THAW]");
   }
 }
 
+fn update_thawed_system(mut thawed: ResMut<Events<Thawed>>) {
+  thawed.update();
+}
+
+fn unlabel_system(mut query: Query<&Name>, commands: &mut Commands) {
+  for (id, name) in query.iter() {
+    if name.0 == "Label" {
+      commands.despawn(id);
+    }
+  }
+}
+
+fn frame_system(frame: Res<FrameCount>) {
+  println!("[FRAME] {}", frame.0);
+}
+
 fn main() {
   let mut world = World::new();
 
-  let point = Entity::new(
-    Component::new()
-      .with_position(Position(3, 4))
-  );
+  world.spawn_bundle((Position(3, 4), Velocity(1, 0)));
 
-  let label = Entity::new(
-    Component::new()
+  world.spawn_bundle(
+    Spawn::new()
       .with_name(Name(String::from("Label")))
+      .with_frozen(Frozen(2)),
   );
 
-  let player = Entity::new(
-    Component::new()
-      .with_position(Position(0, 0))
-      .with_name(Name(String::from("Ian")))
-  );
+  let player = world.spawn_bundle(PlayerBundle {
+    pos: Position(0, 0),
+    vel: Velocity(0, 1),
+    name: Name(String::from("Ian")),
+    player: Player,
+  });
 
-  world.spawn(point);
-  world.spawn(label);
-  world.spawn(player);
-  
   println!("{}", vec!["-"; 50].join(""));
   
-  movement_system(&world);
+  world.insert_resource(Events::<Thawed>::new());
+
+  let mut schedule = Schedule::new();
+  world.add_plugin(&mut schedule, DefaultPlugins);
+  schedule.add_system(frame_system);
+  schedule.add_system(thaw_system);
+  schedule.add_system(movement_system);
+  schedule
+    .add_system_after(thaw_log_system, thaw_system)
+    .expect("thaw systems are acyclic");
+  schedule
+    .add_system_after(update_thawed_system, thaw_log_system)
+    .expect("thaw systems are acyclic");
+
+  for _ in 0..3 {
+    schedule.run(&mut world);
+    println!("{}", vec!["-"; 50].join(""));
+  }
+
+  if let Some(pos) = world.get::<Position>(player) {
+    println!("(ID: {})", player);
+    println!("[PLAYER] {:?}", pos);
+  }
   println!("{}", vec!["-"; 50].join(""));
 
   greet_system(&world);
@@ -139,4 +159,18 @@ fn main() {
 
   render_system(&world);
   println!("{}", vec!["-"; 50].join(""));
+
+  projectile_system(&world);
+  println!("{}", vec!["-"; 50].join(""));
+
+  world.run_system(unlabel_system);
+
+  greet_system(&world);
+  println!("{}", vec!["-"; 50].join(""));
+
+  world.remove_component::<Name>(player);
+
+  greet_system(&world);
+  world.run_system(movement_system);
+  println!("{}", vec!["-"; 50].join(""));
 }
@@ -0,0 +1,254 @@
+use core::any::{Any, TypeId};
+use core::error::Error;
+use core::fmt;
+
+use crate::{Children, EntityId, HashMap, Parent, World};
+
+/// Which value to keep when both sides hold a resource, or a component of
+/// the same entity, of the same type. Not comparable, since function
+/// pointers have no meaningful equality.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ConflictPolicy {
+  #[default]
+  KeepExisting,
+  PreferIncoming,
+  /// Decides each conflict from the type and the existing and incoming
+  /// values, in that order.
+  Custom(fn(TypeId, &dyn Any, &dyn Any) -> MergeResult),
+}
+
+/// The side a `ConflictPolicy::Custom` resolver keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeResult {
+  Existing,
+  Incoming,
+}
+
+impl ConflictPolicy {
+  /// Whether to replace `existing` with `incoming`, both of type `ty`.
+  pub(crate) fn prefers_incoming(self, ty: TypeId, existing: &dyn Any, incoming: &dyn Any) -> bool {
+    match self {
+      ConflictPolicy::KeepExisting => false,
+      ConflictPolicy::PreferIncoming => true,
+      ConflictPolicy::Custom(resolve) => resolve(ty, existing, incoming) == MergeResult::Incoming,
+    }
+  }
+}
+
+/// Why `World::merge_preserving_ids` refused to merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+  /// The id is alive in this world, or its slot has already been used by a
+  /// newer generation, so keeping it could revive a stale id.
+  IdCollision(EntityId),
+}
+
+impl fmt::Display for MergeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      MergeError::IdCollision(id) => write!(f, "entity {} is already taken", id),
+    }
+  }
+}
+
+impl Error for MergeError {}
+
+impl World {
+  /// Moves every entity of `other` into this world under a newly spawned id
+  /// and returns the old ids mapped to the new ones. `Parent`s are pointed at
+  /// the new ids, but other components that refer to entities still hold the
+  /// old ones and have to be fixed up through the returned map. Resources
+  /// only present in `other` are moved over; `policy` decides the ones
+  /// present in both.
+  pub fn merge(&mut self, mut other: World, policy: ConflictPolicy) -> HashMap<EntityId, EntityId> {
+    let ids: HashMap<_, _> = other.entities.iter().map(|&old| (old, self.spawn())).collect();
+    self.deferring_observers(|world| world.merge_entities(&mut other, &ids));
+    self.merge_resources(other, policy);
+    ids
+  }
+
+  /// Like `merge`, but keeps the ids of `other`'s entities, so components
+  /// referring to them stay valid. Nothing is merged if any of them is taken.
+  pub fn merge_preserving_ids(&mut self, mut other: World, policy: ConflictPolicy) -> Result<(), MergeError> {
+    if let Some(&id) = other.entities.iter().find(|&&id| !self.is_free(id)) {
+      return Err(MergeError::IdCollision(id));
+    }
+    let ids: HashMap<_, _> = other.entities.iter().map(|&id| (id, id)).collect();
+    for &id in other.entities() {
+      self.spawn_at(id);
+    }
+    self.deferring_observers(|world| world.merge_entities(&mut other, &ids));
+    self.merge_resources(other, policy);
+    Ok(())
+  }
+
+  /// Whether `id` can be spawned as is without reviving a stale id.
+  pub(crate) fn is_free(&self, id: EntityId) -> bool {
+    match self.generations.get(id.index as usize) {
+      Some(&generation) => self.free.contains(&id.index) && generation <= id.generation,
+      None => true,
+    }
+  }
+
+  /// Spawns exactly `id`, which must be free.
+  pub(crate) fn spawn_at(&mut self, id: EntityId) {
+    let index = id.index as usize;
+    if index < self.generations.len() {
+      self.free.retain(|&free| free != id.index);
+    } else {
+      self.free.extend(self.generations.len() as u32..id.index);
+      self.generations.resize(index + 1, 0);
+    }
+    self.generations[index] = id.generation;
+    self.push_entity(id);
+    self.archetypes.insert(id);
+  }
+
+  /// Moves the components of every entity of `other` to the entity `ids`
+  /// maps it to.
+  fn merge_entities(&mut self, other: &mut World, ids: &HashMap<EntityId, EntityId>) {
+    for (ty, tagged) in core::mem::take(&mut other.tags) {
+      self.tags.entry(ty).or_default().extend(tagged.iter().map(|old| ids[old]));
+    }
+    for old in other.entities.clone() {
+      let new = ids[&old];
+      for ty in other.component_types(old) {
+        if ty == TypeId::of::<Children>() {
+          // Rebuilt from the children's `Parent`s.
+          continue;
+        }
+        if !self.archetypes.is_table(ty) && !self.archetypes.is_sparse(ty) {
+          let name = other.archetypes.name(ty);
+          match other.archetypes.table(ty) {
+            Some(new_column) => self.archetypes.set_table(ty, name, Some(new_column)),
+            None => {
+              self.archetypes.set_table(ty, name, None);
+              self.components.insert(ty, other.components[&ty].empty());
+            }
+          }
+        }
+        if let Some(mut component) = other.take_boxed(old, ty) {
+          if let Some(Parent(parent)) = component.downcast_mut::<Parent>() {
+            *parent = ids.get(parent).copied().unwrap_or(*parent);
+          }
+          self.insert_boxed(new, ty, component);
+        }
+      }
+    }
+  }
+
+  fn merge_resources(&mut self, other: World, policy: ConflictPolicy) {
+    self.resource_names.extend(other.resource_names);
+    for (ty, resource) in other.resources {
+      let replace = match self.resources.get(&ty) {
+        Some(existing) => policy.prefers_incoming(ty, &**existing, &*resource),
+        None => true,
+      };
+      if replace {
+        self.resources.insert(ty, resource);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::query;
+
+  #[derive(Debug, PartialEq)]
+  struct Position(i32, i32);
+
+  #[derive(Debug, PartialEq)]
+  struct Score(u32);
+
+  fn scene(positions: &[i32]) -> World {
+    let mut world = World::new();
+    for &x in positions {
+      world.spawn_bundle((Position(x, 0),));
+    }
+    world
+  }
+
+  fn xs(world: &World) -> Vec<i32> {
+    let mut xs: Vec<i32> = query!(world, Position).map(|(_, pos)| pos.0).collect();
+    xs.sort();
+    xs
+  }
+
+  #[test]
+  fn merge_remaps_ids_and_parents() {
+    let mut world = scene(&[0, 1]);
+    let mut other = scene(&[2]);
+    let ship = other.spawn_bundle((Position(3, 0),));
+    let turret = other.spawn_bundle((Position(4, 0),));
+    other.set_parent(turret, ship);
+
+    let ids = world.merge(other, ConflictPolicy::default());
+
+    assert_eq!(world.len(), 5);
+    assert_eq!(xs(&world), [0, 1, 2, 3, 4]);
+    assert_eq!(world.get::<Parent>(ids[&turret]), Some(&Parent(ids[&ship])));
+    assert_eq!(world.children(ids[&ship]).collect::<Vec<_>>(), [ids[&turret]]);
+  }
+
+  #[test]
+  fn merge_preserving_ids_keeps_ids_or_refuses() {
+    let mut world = scene(&[0]);
+    let taken = world.entities()[0];
+    assert_eq!(
+      world.merge_preserving_ids(scene(&[9]), ConflictPolicy::default()),
+      Err(MergeError::IdCollision(taken))
+    );
+    assert_eq!(xs(&world), [0]);
+
+    let mut other = World::new();
+    let gone = other.spawn();
+    let kept = other.spawn_bundle((Position(1, 0),));
+    other.despawn(gone);
+    world.merge_preserving_ids(other, ConflictPolicy::default()).unwrap();
+
+    assert_eq!(world.get::<Position>(kept), Some(&Position(1, 0)));
+    assert_eq!(world.get::<Position>(taken), Some(&Position(0, 0)));
+    assert_eq!(world.len(), 2);
+  }
+
+  #[test]
+  fn policies_decide_shared_resources() {
+    fn higher(_: TypeId, existing: &dyn Any, incoming: &dyn Any) -> MergeResult {
+      let score = |value: &dyn Any| value.downcast_ref::<Score>().map_or(0, |score| score.0);
+      if score(incoming) > score(existing) { MergeResult::Incoming } else { MergeResult::Existing }
+    }
+    let with_score = |score| {
+      let mut world = World::new();
+      world.insert_resource(Score(score));
+      world
+    };
+
+    let mut world = with_score(1);
+    world.merge(with_score(2), ConflictPolicy::KeepExisting);
+    assert_eq!(world.resource::<Score>(), &Score(1));
+    world.merge(with_score(3), ConflictPolicy::PreferIncoming);
+    assert_eq!(world.resource::<Score>(), &Score(3));
+    world.merge(with_score(2), ConflictPolicy::Custom(higher));
+    assert_eq!(world.resource::<Score>(), &Score(3));
+    world.merge(with_score(5), ConflictPolicy::Custom(higher));
+    assert_eq!(world.resource::<Score>(), &Score(5));
+  }
+
+  struct Boss;
+
+  impl crate::Tag for Boss {}
+
+  #[test]
+  fn tags_follow_merged_entities() {
+    let mut world = scene(&[1]);
+    let mut other = scene(&[2, 3]);
+    let boss = other.entities()[1];
+    other.tag::<Boss>(boss);
+    let ids = world.merge(other, ConflictPolicy::KeepExisting);
+
+    assert!(world.has_tag::<Boss>(ids[&boss]));
+    assert_eq!(world.tag_count::<Boss>(), 1);
+  }
+}
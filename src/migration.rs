@@ -0,0 +1,144 @@
+use alloc::boxed::Box;
+use core::any::{type_name, Any, TypeId};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Component, Shared, World};
+
+/// Type-erased to hold the typed function given to
+/// `World::register_migration`, and shared so that cloned worlds keep it.
+type MigrateFn = Shared<dyn Fn(Box<dyn Any>) -> Box<dyn Any> + Send + Sync>;
+
+/// Turns a component of one type into its successor.
+#[derive(Clone)]
+pub(crate) struct Migration {
+  to: TypeId,
+  migrate: MigrateFn,
+}
+
+impl World {
+  /// Upgrades every `Old` loaded from a snapshot into a `New` through
+  /// `migrate`, e.g. after a component changed shape between releases.
+  /// `Old` has to stay registered under the name saves used for it, and
+  /// `New` is registered under `name`, so that later saves keep the
+  /// migrated values. Migrations chain, so v1 is loaded as v3 if both v1 to
+  /// v2 and v2 to v3 are registered. Panics if the migration would close a
+  /// cycle.
+  pub fn register_migration<Old, New>(&mut self, name: &'static str, migrate: fn(Old) -> New)
+  where
+    Old: Component,
+    New: Component + Serialize + DeserializeOwned,
+  {
+    let from = TypeId::of::<Old>();
+    let mut ty = TypeId::of::<New>();
+    loop {
+      if ty == from {
+        panic!(
+          "migrating `{}` to `{}` would form a cycle",
+          type_name::<Old>(),
+          type_name::<New>()
+        );
+      }
+      match self.migrations.get(&ty) {
+        Some(migration) => ty = migration.to,
+        None => break,
+      }
+    }
+    self.register_component::<New>(name);
+    self.migrations.insert(from, Migration {
+      to: TypeId::of::<New>(),
+      migrate: Shared::new(move |old| {
+        let old = old.downcast::<Old>().expect("migrated from its own type");
+        Box::new(migrate(*old))
+      }),
+    });
+  }
+
+  /// Applies every migration registered for `ty`, in order.
+  pub(crate) fn migrate(&self, mut ty: TypeId, mut component: Box<dyn Any>) -> (TypeId, Box<dyn Any>) {
+    while let Some(migration) = self.migrations.get(&ty) {
+      component = (migration.migrate)(component);
+      ty = migration.to;
+    }
+    (ty, component)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::{Deserialize, Serialize};
+
+  use crate::{query, World};
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct PositionV1 {
+    x: i32,
+    y: i32,
+  }
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct PositionV2(i32, i32);
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct PositionV3 {
+    xy: (i32, i32),
+  }
+
+  fn old_save() -> crate::WorldSnapshot {
+    let mut world = World::new();
+    world.register_component::<PositionV1>("position");
+    world.spawn_bundle((PositionV1 { x: 1, y: 2 },));
+    world.snapshot()
+  }
+
+  #[test]
+  fn migrations_load_old_saves_into_a_fresh_world() {
+    let mut world = World::new();
+    world.register_component::<PositionV1>("position");
+    world.register_migration("position_v2", |old: PositionV1| PositionV2(old.x, old.y));
+    world.restore(&old_save()).unwrap();
+
+    let positions: Vec<PositionV2> = query!(world, PositionV2).map(|(_, pos)| pos.clone()).collect();
+    assert_eq!(positions, [PositionV2(1, 2)]);
+    assert_eq!(query!(world, PositionV1).count(), 0);
+  }
+
+  #[test]
+  fn migrations_chain_in_order() {
+    let mut world = World::new();
+    world.register_component::<PositionV1>("position");
+    world.register_migration("position_v3", |old: PositionV2| PositionV3 { xy: (old.0, old.1) });
+    world.register_migration("position_v2", |old: PositionV1| PositionV2(old.x, old.y));
+    world.restore(&old_save()).unwrap();
+
+    let positions: Vec<PositionV3> = query!(world, PositionV3).map(|(_, pos)| pos.clone()).collect();
+    assert_eq!(positions, [PositionV3 { xy: (1, 2) }]);
+  }
+
+  #[test]
+  #[should_panic(expected = "would form a cycle")]
+  fn cyclic_migrations_panic() {
+    let mut world = World::new();
+    world.register_migration("position_v2", |old: PositionV1| PositionV2(old.x, old.y));
+    world.register_migration("position", |old: PositionV2| PositionV1 { x: old.0, y: old.1 });
+  }
+
+  #[test]
+  fn migrated_values_survive_the_next_save() {
+    let mut world = World::new();
+    world.register_component::<PositionV1>("position");
+    world.register_migration("position_v2", |old: PositionV1| PositionV2(old.x, old.y));
+    world.restore(&old_save()).unwrap();
+    let resaved = world.snapshot();
+    assert_eq!(resaved.entities[0].components["position_v2"], serde_json::json!([1, 2]));
+
+    let mut reloaded = World::new();
+    reloaded.register_component::<PositionV1>("position");
+    reloaded.register_migration("position_v2", |old: PositionV1| PositionV2(old.x, old.y));
+    reloaded.restore(&resaved).unwrap();
+    assert_eq!(reloaded.snapshot(), resaved);
+    let positions: Vec<PositionV2> = query!(reloaded, PositionV2).map(|(_, pos)| pos.clone()).collect();
+    assert_eq!(positions, [PositionV2(1, 2)]);
+  }
+}
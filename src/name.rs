@@ -0,0 +1,122 @@
+use alloc::string::String;
+use core::fmt;
+use core::ops::Deref;
+
+use crate::{EntityId, World};
+
+/// A human-readable name that `World::find_by_name` can look entities up by.
+///
+/// The world indexes names as they are added, replaced and removed, so change
+/// a name by adding a new one or through `Commands::update` rather than by
+/// assigning through `get_mut`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Name(String);
+
+impl Name {
+  pub fn new(name: impl Into<String>) -> Self {
+    Self(name.into())
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+}
+
+impl Deref for Name {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl fmt::Display for Name {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl World {
+  /// The entity named `name`, found without scanning. If several entities
+  /// share a name, the one that was given it last is returned.
+  pub fn find_by_name(&self, name: &str) -> Option<EntityId> {
+    // A name assigned through `get_mut` bypasses the index.
+    self.names
+      .get(name)?
+      .iter()
+      .rev()
+      .copied()
+      .find(|&id| self.get::<Name>(id).is_some_and(|current| current.as_str() == name))
+  }
+
+  pub(crate) fn index_name(&mut self, id: EntityId, name: String) {
+    let ids = self.names.entry(name).or_default();
+    ids.retain(|&other| other != id);
+    ids.push(id);
+  }
+
+  /// Indexes the current name of `id` after it was changed in place.
+  pub(crate) fn reindex_name(&mut self, id: EntityId) {
+    if let Some(name) = self.get::<Name>(id) {
+      let name = name.0.clone();
+      self.index_name(id, name);
+    }
+  }
+
+  /// Removes `id` from the index under its current name, keeping the other
+  /// entities of that name.
+  pub(crate) fn unindex_name(&mut self, id: EntityId) {
+    let Some(name) = self.get::<Name>(id).map(|name| name.0.clone()) else {
+      return;
+    };
+    let Some(ids) = self.names.get_mut(&name) else {
+      return;
+    };
+    ids.retain(|&other| other != id);
+    if ids.is_empty() {
+      self.names.remove(&name);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Commands;
+
+  #[test]
+  fn renaming_moves_the_entity_in_the_index() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Name::new("old"),));
+
+    world.update::<Name>(id, |name| *name = Name::new("new"));
+    assert_eq!(world.find_by_name("new"), Some(id));
+    assert_eq!(world.find_by_name("old"), None);
+    assert!(!world.names.contains_key("old"));
+
+    let mut commands = Commands::new();
+    commands.update::<Name>(id, |name| *name = Name::new("newer"));
+    world.apply_commands(commands);
+    assert_eq!(world.find_by_name("newer"), Some(id));
+    assert_eq!(world.find_by_name("new"), None);
+
+    world.add_component(id, Name::new("latest"));
+    assert_eq!(world.find_by_name("latest"), Some(id));
+    assert_eq!(world.names.len(), 1);
+  }
+
+  #[test]
+  fn duplicate_names_resolve_to_the_latest_remaining_entity() {
+    let mut world = World::new();
+    let first = world.spawn_bundle((Name::new("guard"),));
+    let second = world.spawn_bundle((Name::new("guard"),));
+    assert_eq!(world.find_by_name("guard"), Some(second));
+
+    world.despawn(second);
+    assert_eq!(world.find_by_name("guard"), Some(first));
+    world.remove_component::<Name>(first);
+    assert_eq!(world.find_by_name("guard"), None);
+    assert!(world.names.is_empty());
+  }
+}
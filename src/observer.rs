@@ -0,0 +1,293 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::mem;
+
+use crate::{Commands, Component, EntityId, MaybeSync, Shared, World};
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) type Observer = Shared<dyn Fn(EntityId, &dyn Any, &mut Commands)>;
+#[cfg(feature = "parallel")]
+pub(crate) type Observer = Shared<dyn Fn(EntityId, &dyn Any, &mut Commands) + Send + Sync>;
+
+/// Identifies an observer registered with `World::observe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+/// How many times observer commands may trigger further observers before the
+/// world gives up on them settling.
+const MAX_OBSERVER_ROUNDS: usize = 64;
+
+impl World {
+  /// Runs `observer` whenever a `T` is set on an entity: when it is added or
+  /// replaced, and after `World::update` or `Commands::update` changes it.
+  /// Values mutated in place through `get_mut`, `query_mut!` or a
+  /// `Query<&mut T>` are observed at the next `World::clear_trackers`, which
+  /// `DefaultPlugins` calls at the start of every frame. They are observed
+  /// once per entity however often they were borrowed, since the world
+  /// cannot tell a mutable borrow that wrote from one that only read.
+  ///
+  /// The observer sees the new value and queues its own changes to the world.
+  /// They are applied after the operation that triggered it has finished, so
+  /// an observer of a component spawned in a bundle sees the whole bundle.
+  /// Changes that trigger observers in turn are applied round by round, and
+  /// observers that keep triggering each other panic after 64 rounds.
+  pub fn observe<T, F>(&mut self, observer: F) -> ObserverId
+  where
+    T: Component,
+    F: Fn(EntityId, &T, &mut Commands) + MaybeSync + 'static,
+  {
+    self.changes.entry(TypeId::of::<T>()).or_default().set_observed(true);
+    let id = ObserverId(self.next_observer);
+    self.next_observer += 1;
+    let observer: Observer = Shared::new(move |entity, component: &dyn Any, commands: &mut Commands| {
+      let component = component.downcast_ref::<T>().expect("observed for its own type");
+      observer(entity, component, commands)
+    });
+    self.observers.entry(TypeId::of::<T>()).or_default().push((id, observer));
+    id
+  }
+
+  /// Unregisters an observer, returning whether it was still registered.
+  pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+    for (ty, observers) in &mut self.observers {
+      if let Some(index) = observers.iter().position(|(other, _)| *other == id) {
+        observers.remove(index);
+        if observers.is_empty() {
+          self.changes.entry(*ty).or_default().set_observed(false);
+        }
+        return true;
+      }
+    }
+    false
+  }
+
+  /// Runs the observers of `ty` on the current value of `id`'s component.
+  pub(crate) fn notify(&mut self, id: EntityId, ty: TypeId) {
+    if let Some(changed) = self.changes.get_mut(&ty) {
+      changed.seen(id);
+    }
+    let Some(observers) = self.observers.get(&ty).filter(|observers| !observers.is_empty()) else {
+      return;
+    };
+    let observers: Vec<Observer> = observers.iter().map(|(_, observer)| observer.clone()).collect();
+    let Some(component) = self.get_dyn(id, ty) else {
+      return;
+    };
+    let mut commands = Commands::new();
+    for observer in &observers {
+      observer(id, component, &mut commands);
+    }
+    self.observed().queue.append(&mut commands.queue);
+    self.flush_observed();
+  }
+
+  /// Runs the observers of every value that was borrowed mutably in place
+  /// since they last saw it, in spawn order.
+  pub(crate) fn notify_mutated(&mut self) {
+    let mut mutated = vec![];
+    for (&ty, changed) in &mut self.changes {
+      mutated.extend(changed.take_mutated().into_iter().map(|id| (id, ty)));
+    }
+    let spawn_order = &self.spawn_order;
+    mutated.sort_by_key(|(id, _)| spawn_order[id.index as usize]);
+    self.deferring_observers(|world| {
+      for (id, ty) in mutated {
+        world.notify(id, ty);
+      }
+    });
+  }
+
+  /// Runs `f`, holding back the commands of the observers it triggers until
+  /// it has returned.
+  pub(crate) fn deferring_observers<R>(&mut self, f: impl FnOnce(&mut World) -> R) -> R {
+    self.observing += 1;
+    let result = f(self);
+    self.observing -= 1;
+    self.flush_observed();
+    result
+  }
+
+  fn observed(&mut self) -> &mut Commands {
+    #[cfg(feature = "parallel")]
+    let observed = self.observed.get_mut().unwrap();
+    #[cfg(not(feature = "parallel"))]
+    let observed = self.observed.get_mut();
+    observed
+  }
+
+  /// Applies the queued observer commands unless an operation is still
+  /// holding them back.
+  fn flush_observed(&mut self) {
+    if self.observing > 0 {
+      return;
+    }
+    for _ in 0..MAX_OBSERVER_ROUNDS {
+      let commands = mem::take(self.observed());
+      if commands.queue.is_empty() {
+        return;
+      }
+      self.observing += 1;
+      self.run_commands(commands);
+      self.observing -= 1;
+    }
+    let observed = &mut self.observed().queue;
+    if !observed.is_empty() {
+      observed.clear();
+      panic!("observers kept triggering each other for {} rounds", MAX_OBSERVER_ROUNDS);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use super::*;
+
+  #[derive(Debug, Clone, Copy, PartialEq)]
+  struct Health(i32);
+
+  #[derive(Debug, Clone, Copy, PartialEq)]
+  struct Armor(i32);
+
+  #[derive(Debug, Clone, Copy, PartialEq)]
+  struct Dead;
+
+  #[test]
+  fn every_observer_of_a_type_sees_the_new_value() {
+    let mut world = World::new();
+    let seen = Arc::new(Mutex::new(vec![]));
+    for tag in ["first", "second"] {
+      let seen = seen.clone();
+      world.observe::<Health, _>(move |_, health, _| seen.lock().unwrap().push((tag, health.0)));
+    }
+    let id = world.spawn();
+    world.add_component(id, Health(3));
+    world.update::<Health>(id, |health| health.0 -= 1);
+    assert_eq!(*seen.lock().unwrap(), [("first", 3), ("second", 3), ("first", 2), ("second", 2)]);
+  }
+
+  #[test]
+  fn removed_observers_stop_running() {
+    let mut world = World::new();
+    let seen = Arc::new(Mutex::new(0));
+    let counter = seen.clone();
+    let observer = world.observe::<Health, _>(move |_, _, _| *counter.lock().unwrap() += 1);
+    world.spawn_bundle((Health(1),));
+    assert!(world.remove_observer(observer));
+    assert!(!world.remove_observer(observer));
+    world.spawn_bundle((Health(1),));
+    assert_eq!(*seen.lock().unwrap(), 1);
+  }
+
+  #[test]
+  fn commands_are_applied_once_the_bundle_is_in_place() {
+    let mut world = World::new();
+    world.observe::<Health, _>(|id, _, commands| {
+      commands.update::<Armor>(id, |armor| armor.0 *= 2);
+    });
+    let id = world.spawn_bundle((Health(1), Armor(3)));
+    assert_eq!(world.get::<Armor>(id), Some(&Armor(6)));
+  }
+
+  #[test]
+  fn chained_observers_run_in_later_rounds() {
+    let mut world = World::new();
+    world.observe::<Health, _>(|id, health, commands| {
+      if health.0 <= 0 {
+        commands.add_component(id, Dead);
+      }
+    });
+    world.observe::<Dead, _>(|id, _, commands| commands.remove_component::<Health>(id));
+    let id = world.spawn_bundle((Health(1),));
+    world.update::<Health>(id, |health| health.0 -= 1);
+    assert_eq!(world.get::<Dead>(id), Some(&Dead));
+    assert_eq!(world.get::<Health>(id), None);
+  }
+
+  #[test]
+  #[should_panic(expected = "observers kept triggering each other")]
+  fn observers_that_never_settle_panic() {
+    let mut world = World::new();
+    world.observe::<Health, _>(|id, _, commands| commands.add_component(id, Armor(0)));
+    world.observe::<Armor, _>(|id, _, commands| commands.add_component(id, Health(0)));
+    world.spawn_bundle((Health(0),));
+  }
+
+  fn observed_health(world: &mut World) -> Arc<Mutex<Vec<(EntityId, i32)>>> {
+    let seen = Arc::new(Mutex::new(vec![]));
+    let log = seen.clone();
+    world.observe::<Health, _>(move |id, health, _| log.lock().unwrap().push((id, health.0)));
+    seen
+  }
+
+  #[test]
+  fn values_mutated_in_place_are_observed_at_the_next_clear_trackers() {
+    use crate::query_mut;
+
+    let mut world = World::new();
+    let a = world.spawn_bundle((Health(1),));
+    let b = world.spawn_bundle((Health(2), Armor(0)));
+    let seen = observed_health(&mut world);
+
+    world.get_mut::<Health>(b).unwrap().0 = 5;
+    world.get_mut::<Health>(b).unwrap().0 += 1;
+    for (_, health) in query_mut!(world, Health) {
+      health.0 *= 10;
+    }
+    assert!(seen.lock().unwrap().is_empty());
+    world.clear_trackers();
+    assert_eq!(*seen.lock().unwrap(), [(a, 10), (b, 60)]);
+    world.clear_trackers();
+    assert_eq!(seen.lock().unwrap().len(), 2);
+  }
+
+  #[test]
+  fn systems_writing_through_queries_are_observed() {
+    use crate::Query;
+
+    fn heal(mut query: Query<&mut Health>) {
+      for (_, health) in query.iter() {
+        health.0 += 1;
+      }
+    }
+
+    let mut world = World::new();
+    let id = world.spawn_bundle((Health(1),));
+    let seen = observed_health(&mut world);
+    world.run_system(heal);
+    world.clear_trackers();
+    assert_eq!(*seen.lock().unwrap(), [(id, 2)]);
+  }
+
+  #[test]
+  fn in_place_changes_are_observed_once_per_value() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Health(1),));
+    let seen = observed_health(&mut world);
+    world.get_mut::<Health>(id).unwrap().0 = 2;
+    world.update::<Health>(id, |health| health.0 = 3);
+    world.clear_trackers();
+    assert_eq!(*seen.lock().unwrap(), [(id, 3)]);
+
+    world.get_mut::<Health>(id).unwrap().0 = 4;
+    world.despawn(id);
+    world.clear_trackers();
+    assert_eq!(seen.lock().unwrap().len(), 1);
+  }
+
+  #[test]
+  fn observer_commands_from_in_place_changes_count_towards_the_new_frame() {
+    let mut world = World::new();
+    world.observe::<Health, _>(|id, health, commands| {
+      if health.0 <= 0 {
+        commands.add_component(id, Dead);
+      }
+    });
+    let id = world.spawn_bundle((Health(1),));
+    world.get_mut::<Health>(id).unwrap().0 = 0;
+    world.clear_trackers();
+    assert!(world.is_added::<Dead>(id));
+  }
+}
@@ -0,0 +1,87 @@
+use alloc::boxed::Box;
+use core::any::{type_name, TypeId};
+
+use crate::{Command, Commands, ResMut, Schedule, World};
+
+/// A self-contained piece of game setup, such as the systems, resources and
+/// events of one feature, added with `World::add_plugin`.
+pub trait Plugin: 'static {
+  fn build(&self, world: &mut World, schedule: &mut Schedule);
+}
+
+/// The number of frames the schedule has started, kept by `DefaultPlugins`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameCount(pub u64);
+
+/// What every game needs: a `FrameCount` and a system that starts each
+/// frame by advancing it and clearing the change trackers of the last one.
+/// Add it before other plugins and systems so that its system runs first.
+pub struct DefaultPlugins;
+
+fn frame_start_system(mut frame: ResMut<FrameCount>, commands: &mut Commands) {
+  frame.0 += 1;
+  commands.push(Command::Update(Box::new(World::clear_trackers)));
+}
+
+impl Plugin for DefaultPlugins {
+  fn build(&self, world: &mut World, schedule: &mut Schedule) {
+    world.insert_resource(FrameCount::default());
+    schedule.add_system(frame_start_system);
+  }
+}
+
+impl World {
+  /// Builds `plugin` into this world and `schedule`. Panics if a plugin of
+  /// the same type was already added.
+  pub fn add_plugin<P: Plugin>(&mut self, schedule: &mut Schedule, plugin: P) {
+    if !self.plugins.insert(TypeId::of::<P>()) {
+      panic!("plugin `{}` was already added", type_name::<P>());
+    }
+    plugin.build(self, schedule);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use core::time::Duration;
+
+  use super::*;
+
+  #[derive(Debug, PartialEq)]
+  struct Position(i32);
+
+  struct Spawner;
+
+  impl Plugin for Spawner {
+    fn build(&self, world: &mut World, _schedule: &mut Schedule) {
+      world.spawn_bundle((Position(0),));
+    }
+  }
+
+  #[test]
+  fn default_plugins_count_frames_and_clear_trackers() {
+    let mut world = World::new();
+    let mut schedule = Schedule::new();
+    world.add_plugin(&mut schedule, DefaultPlugins);
+    world.add_plugin(&mut schedule, Spawner);
+    let id = world.spawn_bundle((Position(1),));
+    assert!(world.is_added::<Position>(id));
+
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<FrameCount>(), &FrameCount(1));
+    assert!(!world.is_added::<Position>(id));
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<FrameCount>(), &FrameCount(2));
+    assert_eq!(world.len(), 2);
+    assert_eq!(world.get::<Position>(id), Some(&Position(1)));
+  }
+
+  #[test]
+  #[should_panic(expected = "was already added")]
+  fn plugins_can_only_be_added_once() {
+    let mut world = World::new();
+    let mut schedule = Schedule::new();
+    world.add_plugin(&mut schedule, Spawner);
+    world.add_plugin(&mut schedule, Spawner);
+  }
+}
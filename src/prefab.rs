@@ -0,0 +1,142 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{EntityId, EntitySnapshot, HashMap, SnapshotError, World};
+
+/// A reusable group of entities, e.g. an enemy type or a UI widget, stored
+/// like a `WorldSnapshot`. Entity ids inside its components, such as a
+/// `Parent`, refer to the prefab's own entities and are remapped to the new
+/// ones on every instantiation.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Prefab {
+  pub entities: Vec<EntitySnapshot>,
+}
+
+impl Prefab {
+  /// Captures `ids` along with their registered components.
+  pub fn from_entities(world: &World, ids: &[EntityId]) -> Prefab {
+    let entities = ids
+      .iter()
+      .filter(|&&id| world.is_alive(id))
+      .map(|&id| world.snapshot_entity(id))
+      .collect();
+    Prefab { entities }
+  }
+}
+
+/// Replaces every serialized `EntityId` in `value` that `ids` maps.
+fn remap(value: &mut Value, ids: &HashMap<EntityId, EntityId>) {
+  if let Ok(id) = serde_json::from_value::<EntityId>(value.clone()) {
+    if let Some(&new) = ids.get(&id) {
+      *value = serde_json::to_value(new).expect("entity ids serialize");
+    }
+    return;
+  }
+  match value {
+    Value::Array(values) => values.iter_mut().for_each(|value| remap(value, ids)),
+    Value::Object(fields) => fields.values_mut().for_each(|value| remap(value, ids)),
+    _ => {}
+  }
+}
+
+impl World {
+  /// Spawns a copy of every entity of `prefab`, returning the new ids in the
+  /// prefab's order. Nothing is spawned if any component fails to load.
+  pub fn from_prefab(&mut self, prefab: &Prefab) -> Result<Vec<EntityId>, SnapshotError> {
+    let ids: Vec<EntityId> = prefab.entities.iter().map(|_| self.spawn()).collect();
+    let remapped: HashMap<EntityId, EntityId> =
+      prefab.entities.iter().map(|entity| entity.id).zip(ids.iter().copied()).collect();
+    let mut components = vec![];
+    for (entity, &id) in prefab.entities.iter().zip(&ids) {
+      for (name, value) in &entity.components {
+        let mut value = value.clone();
+        remap(&mut value, &remapped);
+        match self.load_component(name, &value) {
+          Ok((ty, component)) => components.push((id, ty, component)),
+          Err(error) => {
+            for &id in &ids {
+              self.despawn(id);
+            }
+            return Err(error);
+          }
+        }
+      }
+    }
+    self.deferring_observers(|world| {
+      for (id, ty, component) in components {
+        world.insert_boxed(id, ty, component);
+      }
+    });
+    Ok(ids)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Children, Parent, Relationship};
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct Health(u32);
+
+  struct Guards;
+
+  fn registered() -> World {
+    let mut world = World::new();
+    world.register_component::<Health>("health");
+    world.register_component::<Parent>("parent");
+    world.register_component::<Relationship<Guards>>("guards");
+    world
+  }
+
+  #[test]
+  fn from_entities_captures_the_living_entities() {
+    let mut world = registered();
+    let a = world.spawn_bundle((Health(1),));
+    let gone = world.spawn_bundle((Health(2),));
+    world.despawn(gone);
+
+    let prefab = Prefab::from_entities(&world, &[a, gone]);
+    assert_eq!(prefab.entities.len(), 1);
+    assert_eq!(prefab.entities[0].id, a);
+    assert_eq!(prefab.entities[0].components["health"], serde_json::json!(1));
+  }
+
+  #[test]
+  fn every_instance_points_at_its_own_entities() {
+    let mut world = registered();
+    let root = world.spawn_bundle((Health(10),));
+    let guard = world.spawn_bundle((Health(1),));
+    world.set_parent(guard, root);
+    world.relate::<Guards>(guard, root);
+    let prefab = Prefab::from_entities(&world, &[root, guard]);
+    let json = serde_json::to_string(&prefab).unwrap();
+    let prefab: Prefab = serde_json::from_str(&json).unwrap();
+
+    let first = world.from_prefab(&prefab).unwrap();
+    let second = world.from_prefab(&prefab).unwrap();
+    for ids in [&first, &second] {
+      let [root, guard] = ids[..] else { panic!("two entities per instance") };
+      assert_eq!(world.get::<Parent>(guard), Some(&Parent(root)));
+      assert_eq!(world.related::<Guards>(guard), Some(root));
+      assert_eq!(world.get::<Children>(root).map(|children| &children[..]), Some(&[guard][..]));
+      assert_eq!(world.get::<Health>(root), Some(&Health(10)));
+    }
+    assert_ne!(first, second);
+    assert_eq!(world.len(), 6);
+  }
+
+  #[test]
+  fn failed_instantiations_spawn_nothing() {
+    let mut world = registered();
+    let mut entity = EntitySnapshot { id: world.spawn(), components: Default::default() };
+    entity.components.insert("health".into(), serde_json::json!("full"));
+    let len = world.len();
+
+    assert!(world.from_prefab(&Prefab { entities: vec![entity] }).is_err());
+    assert_eq!(world.len(), len);
+  }
+}
@@ -0,0 +1,89 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::TypeId;
+
+use crate::{AnyResource, Component, EntityId, HashMap, World};
+
+/// How the previous values of one tracked type are taken and copied.
+#[derive(Clone, Copy)]
+pub(crate) struct Tracker {
+  snapshot: fn(&mut World),
+  pub(crate) clone: fn(&AnyResource) -> Box<AnyResource>,
+}
+
+/// Copies the current value of every `T` into the previous values.
+fn snapshot<T: Component + Clone>(world: &mut World) {
+  let values: HashMap<EntityId, T> = world
+    .matching_entities(vec![TypeId::of::<T>()])
+    .filter_map(|id| Some((id, world.get::<T>(id)?.clone())))
+    .collect();
+  world.previous.insert(TypeId::of::<T>(), Box::new(values));
+}
+
+fn clone_previous<T: Component + Clone>(previous: &AnyResource) -> Box<AnyResource> {
+  let previous = previous.downcast_ref::<HashMap<EntityId, T>>().expect("tracked for its own type");
+  Box::new(previous.clone())
+}
+
+impl World {
+  /// Keeps a copy of every `T` as it was at the last `clear_trackers`, e.g.
+  /// to interpolate a `Position` between frames. See `previous` and
+  /// `query_prev!`.
+  pub fn track_previous<T: Component + Clone>(&mut self) {
+    self.snapshots.insert(TypeId::of::<T>(), Tracker {
+      snapshot: snapshot::<T>,
+      clone: clone_previous::<T>,
+    });
+  }
+
+  /// The value `id`'s `T` had at the last `clear_trackers`. `None` for
+  /// entities that got their `T` since, or if `T` is not tracked.
+  pub fn previous<T: Component>(&self, id: EntityId) -> Option<&T> {
+    self.previous
+      .get(&TypeId::of::<T>())?
+      .downcast_ref::<HashMap<EntityId, T>>()?
+      .get(&id)
+  }
+
+  pub(crate) fn snapshot_previous(&mut self) {
+    for tracker in self.snapshots.values().copied().collect::<Vec<_>>() {
+      (tracker.snapshot)(self);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{query_prev, EntityId, World};
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Position(i32, i32);
+
+  #[test]
+  fn previous_values_are_those_at_the_last_clear() {
+    let mut world = World::new();
+    world.track_previous::<Position>();
+    let moved = world.spawn_bundle((Position(0, 0),));
+    world.clear_trackers();
+    world.update::<Position>(moved, |pos| pos.0 = 5);
+    let fresh = world.spawn_bundle((Position(9, 9),));
+
+    let pairs: Vec<(EntityId, Position, Option<Position>)> =
+      query_prev!(world, Position).map(|(id, current, previous)| (id, current.clone(), previous.cloned())).collect();
+    assert_eq!(pairs, [(moved, Position(5, 0), Some(Position(0, 0))), (fresh, Position(9, 9), None)]);
+
+    world.clear_trackers();
+    assert_eq!(world.previous::<Position>(moved), Some(&Position(5, 0)));
+    assert_eq!(world.previous::<Position>(fresh), Some(&Position(9, 9)));
+  }
+
+  #[test]
+  fn untracked_types_have_no_previous_values() {
+    let mut world = World::new();
+    let id = world.spawn_bundle((Position(0, 0),));
+    world.clear_trackers();
+
+    assert_eq!(world.previous::<Position>(id), None);
+  }
+}
@@ -0,0 +1,617 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::TypeId;
+use core::cmp::Ordering;
+use core::error::Error;
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::storage::{Column, SparseSet};
+use crate::system::{Access, SystemParam};
+use crate::{Component, EntityId, MaybeSend, StorageMut, Tag, Ticks, World};
+
+/// The component types a `Query` fetches, e.g. `(&mut Position, &Velocity)`.
+pub trait QueryData {
+  type Fetch: MaybeSend;
+  type View<'f>;
+  type Item<'f>;
+
+  fn access(access: &mut Access);
+
+  fn required(types: &mut Vec<TypeId>);
+
+  fn take(world: &mut World) -> Self::Fetch;
+
+  fn restore(fetch: &mut Self::Fetch, world: &mut World);
+
+  fn view<'f>(fetch: &'f mut Self::Fetch, world: &'f World) -> Self::View<'f>;
+
+  fn contains(view: &Self::View<'_>, id: EntityId) -> bool;
+
+  fn fetch<'f>(view: &mut Self::View<'f>, id: EntityId) -> Option<Self::Item<'f>>;
+}
+
+impl<T: Component> QueryData for &T {
+  type Fetch = ();
+  type View<'f> = &'f World;
+  type Item<'f> = &'f T;
+
+  fn access(access: &mut Access) {
+    access.add_read::<T>();
+  }
+
+  fn required(types: &mut Vec<TypeId>) {
+    types.push(TypeId::of::<T>());
+  }
+
+  fn take(_world: &mut World) {}
+
+  fn restore(_fetch: &mut (), _world: &mut World) {}
+
+  fn view<'f>(_fetch: &'f mut (), world: &'f World) -> &'f World {
+    world
+  }
+
+  fn contains(world: &Self::View<'_>, id: EntityId) -> bool {
+    world.get::<T>(id).is_some()
+  }
+
+  fn fetch<'f>(world: &mut Self::View<'f>, id: EntityId) -> Option<Self::Item<'f>> {
+    world.get::<T>(id)
+  }
+}
+
+impl<T: Component> QueryData for &mut T {
+  type Fetch = (Option<SparseSet>, Vec<(usize, Box<dyn Column>)>, Ticks);
+  type View<'f> = (StorageMut<'f>, &'f mut Ticks, u32);
+  type Item<'f> = &'f mut T;
+
+  fn access(access: &mut Access) {
+    access.add_write::<T>();
+  }
+
+  fn required(types: &mut Vec<TypeId>) {
+    types.push(TypeId::of::<T>());
+  }
+
+  fn take(world: &mut World) -> Self::Fetch {
+    let ty = TypeId::of::<T>();
+    (
+      world.components.remove(&ty),
+      world.archetypes.take_columns(ty),
+      world.changes.remove(&ty).unwrap_or_default(),
+    )
+  }
+
+  fn restore((storage, columns, changed): &mut Self::Fetch, world: &mut World) {
+    let ty = TypeId::of::<T>();
+    if let Some(set) = storage.take() {
+      world.components.insert(ty, set);
+    }
+    world.archetypes.restore_columns(ty, core::mem::take(columns));
+    world.changes.insert(ty, core::mem::take(changed));
+  }
+
+  fn view<'f>((storage, columns, changed): &'f mut Self::Fetch, world: &'f World) -> Self::View<'f> {
+    let mut view: StorageMut = storage.iter_mut().flat_map(|set| set.iter_mut()).collect();
+    let archetypes = world.archetypes();
+    for (archetype, column) in columns {
+      view.extend(archetypes[*archetype].entities().iter().copied().zip(column.iter_mut()));
+    }
+    (view, changed, world.change_tick())
+  }
+
+  fn contains((storage, ..): &Self::View<'_>, id: EntityId) -> bool {
+    storage.contains_key(&id)
+  }
+
+  fn fetch<'f>((storage, changed, tick): &mut Self::View<'f>, id: EntityId) -> Option<Self::Item<'f>> {
+    let component = storage.remove(&id)?.downcast_mut::<T>()?;
+    changed.borrow_mut(id, *tick);
+    Some(component)
+  }
+}
+
+macro_rules! impl_query_data {
+  ($($data:ident),+) => {
+    #[allow(non_snake_case)]
+    impl<$($data: QueryData),+> QueryData for ($($data,)+) {
+      type Fetch = ($($data::Fetch,)+);
+      type View<'f> = ($($data::View<'f>,)+);
+      type Item<'f> = ($($data::Item<'f>,)+);
+
+      /// Panics if the same component is fetched twice with at least one of
+      /// them mutable, which would alias it.
+      fn access(access: &mut Access) {
+        $(
+          let mut item = Access::default();
+          $data::access(&mut item);
+          if let Some(name) = access.conflict_with(&item) {
+            panic!("component `{}` was requested more than once in the same query", name);
+          }
+          access.extend(item);
+        )+
+      }
+
+      fn required(types: &mut Vec<TypeId>) {
+        $($data::required(types);)+
+      }
+
+      fn take(world: &mut World) -> Self::Fetch {
+        ($($data::take(world),)+)
+      }
+
+      fn restore(fetch: &mut Self::Fetch, world: &mut World) {
+        let ($($data,)+) = fetch;
+        $($data::restore($data, world);)+
+      }
+
+      fn view<'f>(fetch: &'f mut Self::Fetch, world: &'f World) -> Self::View<'f> {
+        let ($($data,)+) = fetch;
+        ($($data::view($data, world),)+)
+      }
+
+      fn contains(view: &Self::View<'_>, id: EntityId) -> bool {
+        let ($($data,)+) = view;
+        $($data::contains($data, id))&&+
+      }
+
+      fn fetch<'f>(view: &mut Self::View<'f>, id: EntityId) -> Option<Self::Item<'f>> {
+        if !Self::contains(view, id) {
+          return None;
+        }
+        let ($($data,)+) = view;
+        Some(($($data::fetch($data, id)?,)+))
+      }
+    }
+  };
+}
+
+impl_query_data!(A);
+impl_query_data!(A, B);
+impl_query_data!(A, B, C);
+impl_query_data!(A, B, C, D);
+impl_query_data!(A, B, C, D, E);
+
+/// Restricts which entities a `Query` yields without fetching any data.
+pub trait QueryFilter {
+  fn access(access: &mut Access);
+
+  fn required(_types: &mut Vec<TypeId>) {}
+
+  fn matches(world: &World, id: EntityId) -> bool;
+}
+
+impl QueryFilter for () {
+  fn access(_access: &mut Access) {}
+
+  fn matches(_world: &World, _id: EntityId) -> bool {
+    true
+  }
+}
+
+pub struct With<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+  fn access(access: &mut Access) {
+    access.add_read::<T>();
+  }
+
+  fn required(types: &mut Vec<TypeId>) {
+    types.push(TypeId::of::<T>());
+  }
+
+  fn matches(world: &World, id: EntityId) -> bool {
+    world.get::<T>(id).is_some()
+  }
+}
+
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Without<T> {
+  fn access(access: &mut Access) {
+    access.add_read::<T>();
+  }
+
+  fn matches(world: &World, id: EntityId) -> bool {
+    world.get::<T>(id).is_none()
+  }
+}
+
+/// Matches entities tagged with `T` through `World::tag`.
+pub struct Tagged<T>(PhantomData<T>);
+
+impl<T: Tag> QueryFilter for Tagged<T> {
+  fn access(access: &mut Access) {
+    access.add_read::<T>();
+  }
+
+  fn matches(world: &World, id: EntityId) -> bool {
+    world.has_tag::<T>(id)
+  }
+}
+
+/// Matches entities whose `T` was added or mutably borrowed since the last
+/// `World::clear_trackers`.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Changed<T> {
+  fn access(access: &mut Access) {
+    access.add_read::<T>();
+  }
+
+  fn required(types: &mut Vec<TypeId>) {
+    types.push(TypeId::of::<T>());
+  }
+
+  fn matches(world: &World, id: EntityId) -> bool {
+    world.is_changed::<T>(id)
+  }
+}
+
+/// Matches entities that received `T` since the last
+/// `World::clear_trackers`.
+pub struct Added<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Added<T> {
+  fn access(access: &mut Access) {
+    access.add_read::<T>();
+  }
+
+  fn required(types: &mut Vec<TypeId>) {
+    types.push(TypeId::of::<T>());
+  }
+
+  fn matches(world: &World, id: EntityId) -> bool {
+    world.is_added::<T>(id)
+  }
+}
+
+macro_rules! impl_query_filter {
+  ($($filter:ident),+) => {
+    impl<$($filter: QueryFilter),+> QueryFilter for ($($filter,)+) {
+      fn access(access: &mut Access) {
+        $($filter::access(access);)+
+      }
+
+      fn required(types: &mut Vec<TypeId>) {
+        $($filter::required(types);)+
+      }
+
+      fn matches(world: &World, id: EntityId) -> bool {
+        $($filter::matches(world, id))&&+
+      }
+    }
+  };
+}
+
+impl_query_filter!(A);
+impl_query_filter!(A, B);
+impl_query_filter!(A, B, C);
+impl_query_filter!(A, B, C, D);
+
+pub struct Query<'f, Q: QueryData, F: QueryFilter = ()> {
+  world: &'f World,
+  fetch: &'f mut Q::Fetch,
+  marker: PhantomData<F>,
+}
+
+impl<Q: QueryData, F: QueryFilter> Query<'_, Q, F> {
+  pub fn iter(&mut self) -> impl Iterator<Item = (EntityId, Q::Item<'_>)> {
+    iter::<Q, F>(self.world, self.fetch)
+  }
+}
+
+fn iter<'f, Q: QueryData, F: QueryFilter>(
+  world: &'f World,
+  fetch: &'f mut Q::Fetch,
+) -> impl Iterator<Item = (EntityId, Q::Item<'f>)> {
+  let mut types = vec![];
+  Q::required(&mut types);
+  F::required(&mut types);
+  let mut view = Q::view(fetch, world);
+  world.matching_entities(types).filter_map(move |id| {
+    if !F::matches(world, id) {
+      return None;
+    }
+    Some((id, Q::fetch(&mut view, id)?))
+  })
+}
+
+/// A `Query` run directly on a world rather than from a system, returned by
+/// `World::query`. The storages it fetches mutably are handed back to the
+/// world when it is dropped.
+pub struct WorldQuery<'w, Q: QueryData, F: QueryFilter = ()> {
+  world: &'w mut World,
+  fetch: Q::Fetch,
+  marker: PhantomData<F>,
+}
+
+impl<Q: QueryData, F: QueryFilter> WorldQuery<'_, Q, F> {
+  pub fn iter(&mut self) -> impl Iterator<Item = (EntityId, Q::Item<'_>)> {
+    iter::<Q, F>(self.world, &mut self.fetch)
+  }
+}
+
+impl<Q: QueryData, F: QueryFilter> Drop for WorldQuery<'_, Q, F> {
+  fn drop(&mut self) {
+    Q::restore(&mut self.fetch, self.world);
+  }
+}
+
+impl World {
+  /// Queries the world without the `query!` macro, e.g.
+  /// `world.query::<(&mut Position, &Velocity)>()`.
+  pub fn query<Q: QueryData + 'static>(&mut self) -> WorldQuery<'_, Q> {
+    self.query_filtered::<Q, ()>()
+  }
+
+  /// Like `query`, but only visits the entities matching `F`. Panics if `Q`
+  /// fetches a component more than once with one of them mutable, or if `F`
+  /// filters on a type that `Q` fetches mutably.
+  pub fn query_filtered<Q, F>(&mut self) -> WorldQuery<'_, Q, F>
+  where
+    Q: QueryData + 'static,
+    F: QueryFilter + 'static,
+  {
+    Query::<Q, F>::access(&mut Access::default());
+    let fetch = Q::take(self);
+    WorldQuery { world: self, fetch, marker: PhantomData }
+  }
+}
+
+impl<Q: QueryData + 'static, F: QueryFilter + 'static> SystemParam for Query<'_, Q, F> {
+  type State = ();
+  type Fetch = Q::Fetch;
+  type Item<'f> = Query<'f, Q, F>;
+
+  fn access(access: &mut Access) {
+    // Filters only look at component presence, but a storage fetched mutably
+    // is moved out of the world while the system runs, so filtering on a type
+    // the same query writes is rejected.
+    let mut data = Access::default();
+    Q::access(&mut data);
+    let mut filter = Access::default();
+    F::access(&mut filter);
+    if let Some(name) = data.conflict_with(&filter) {
+      panic!("query filter on `{}` conflicts with the data it fetches", name);
+    }
+    access.extend(data);
+    access.extend(filter);
+  }
+
+  fn take(_state: &mut (), world: &mut World) -> Q::Fetch {
+    Q::take(world)
+  }
+
+  fn get<'f>(fetch: &'f mut Q::Fetch, world: &'f World) -> Query<'f, Q, F> {
+    Query { world, fetch, marker: PhantomData }
+  }
+
+  fn restore(fetch: &mut Q::Fetch, world: &mut World) {
+    Q::restore(fetch, world);
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuerySingleError {
+  NoEntities(&'static str),
+  MultipleEntities(&'static str),
+}
+
+/// The only item of `items`, where `name` describes what was queried.
+#[doc(hidden)]
+pub fn single<I>(mut items: impl Iterator<Item = I>, name: &'static str) -> Result<I, QuerySingleError> {
+  let item = items.next().ok_or(QuerySingleError::NoEntities(name))?;
+  if items.next().is_some() {
+    return Err(QuerySingleError::MultipleEntities(name));
+  }
+  Ok(item)
+}
+
+impl fmt::Display for QuerySingleError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      QuerySingleError::NoEntities(name) => write!(f, "no entity has a `{}`", name),
+      QuerySingleError::MultipleEntities(name) => {
+        write!(f, "more than one entity has a `{}`", name)
+      }
+    }
+  }
+}
+
+impl Error for QuerySingleError {}
+
+/// An item yielded by `query!`, whose components a `; by` or `; by_key`
+/// clause sorts on: the component itself for a single one, or a tuple of
+/// them otherwise.
+#[doc(hidden)]
+pub trait QueryItem {
+  type Components<'a>
+  where
+    Self: 'a;
+
+  fn components(&self) -> Self::Components<'_>;
+}
+
+impl<A> QueryItem for (EntityId, A) {
+  type Components<'a> = &'a A where Self: 'a;
+
+  fn components(&self) -> &A {
+    &self.1
+  }
+}
+
+macro_rules! impl_query_item {
+  ($($name:ident $index:tt),+) => {
+    impl<$($name),+> QueryItem for (EntityId, $($name),+) {
+      type Components<'a> = ($(&'a $name,)+) where Self: 'a;
+
+      fn components(&self) -> Self::Components<'_> {
+        ($(&self.$index,)+)
+      }
+    }
+  };
+}
+
+impl_query_item!(A 1, B 2);
+impl_query_item!(A 1, B 2, C 3);
+impl_query_item!(A 1, B 2, C 3, D 4);
+impl_query_item!(A 1, B 2, C 3, D 4, E 5);
+impl_query_item!(A 1, B 2, C 3, D 4, E 5, F 6);
+
+/// The order that sorts `items` by `compare`, as indices into `items`.
+#[doc(hidden)]
+pub fn order_by<'a, I: QueryItem>(
+  items: &'a [I],
+  mut compare: impl FnMut(I::Components<'a>, I::Components<'a>) -> Ordering,
+) -> Vec<usize>
+where
+  I::Components<'a>: Copy,
+{
+  let components: Vec<_> = items.iter().map(I::components).collect();
+  let mut order: Vec<usize> = (0..items.len()).collect();
+  order.sort_by(|&a, &b| compare(components[a], components[b]));
+  order
+}
+
+/// The order that sorts `items` by `key`, as indices into `items`.
+#[doc(hidden)]
+pub fn order_by_key<'a, I: QueryItem, K: Ord>(
+  items: &'a [I],
+  key: impl FnMut(I::Components<'a>) -> K,
+) -> Vec<usize> {
+  let keys: Vec<K> = items.iter().map(I::components).map(key).collect();
+  let mut order: Vec<usize> = (0..items.len()).collect();
+  order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+  order
+}
+
+/// Rearranges `items` into `order`, which holds each of their indices once.
+#[doc(hidden)]
+pub fn permute<I>(items: Vec<I>, order: Vec<usize>) -> alloc::vec::IntoIter<I> {
+  let mut items: Vec<Option<I>> = items.into_iter().map(Some).collect();
+  let sorted: Vec<I> = order
+    .into_iter()
+    .map(|index| items[index].take().expect("each index appears once"))
+    .collect();
+  sorted.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{query, query_count, query_one, try_query_one};
+
+  #[derive(Debug, PartialEq)]
+  struct Position(i32, i32);
+
+  #[derive(Debug, PartialEq)]
+  struct Label(&'static str);
+
+  #[test]
+  fn query_one_returns_the_single_match() {
+    let mut world = World::new();
+    world.spawn_bundle((Position(1, 1),));
+    let player = world.spawn_bundle((Position(0, 0), Label("player")));
+
+    assert_eq!(query_one!(world, Position, Label), Some((player, &Position(0, 0), &Label("player"))));
+    world.despawn(player);
+    assert_eq!(query_one!(world, Position, Label), None);
+  }
+
+  #[test]
+  fn try_query_one_reports_several_matches() {
+    let mut world = World::new();
+    world.spawn_bundle((Position(0, 0),));
+    world.spawn_bundle((Position(1, 1),));
+
+    assert!(matches!(try_query_one!(world, Position), Err(QuerySingleError::MultipleEntities(_))));
+  }
+
+  #[test]
+  #[should_panic(expected = "more than one entity")]
+  fn query_one_panics_on_several_matches() {
+    let mut world = World::new();
+    world.spawn_bundle((Position(0, 0),));
+    world.spawn_bundle((Position(1, 1),));
+    query_one!(world, Position);
+  }
+
+  #[derive(Debug, PartialEq)]
+  struct Velocity(i32, i32);
+
+  #[test]
+  fn query_count_agrees_with_query() {
+    let mut world = World::new();
+    world.spawn_bundle((Position(0, 0),));
+    world.spawn_bundle((Position(1, 1), Label("a")));
+    world.spawn_bundle((Position(2, 2), Label("b"), Velocity(1, 0)));
+    world.spawn_bundle((Label("c"), Velocity(0, 1)));
+
+    assert_eq!(query_count!(world, Position), query!(world, Position).count());
+    assert_eq!(query_count!(world, Position), 3);
+    assert_eq!(query_count!(world, Position, Label), query!(world, Position, Label).count());
+    assert_eq!(query_count!(world, Position, Label), 2);
+    assert_eq!(query_count!(world, Label; without Position), 1);
+    assert_eq!(query_count!(world, Position, ?Velocity), 3);
+    assert_eq!(query_count!(world, Position, Label, Velocity), 1);
+  }
+
+  #[test]
+  #[should_panic(expected = "component `tecs::query::tests::Position` was requested more than once")]
+  fn queries_cannot_fetch_a_component_mutably_twice() {
+    World::new().query::<(&mut Position, &mut Position)>();
+  }
+
+  #[test]
+  #[should_panic(expected = "component `tecs::query::tests::Position` was requested more than once")]
+  fn queries_cannot_read_a_component_they_write() {
+    World::new().query::<(&Velocity, (&mut Position, &Position))>();
+  }
+
+  #[test]
+  #[should_panic(expected = "component `tecs::query::tests::Position` was requested more than once")]
+  fn systems_with_an_aliasing_query_are_rejected() {
+    use crate::IntoSystem;
+
+    fn alias(_: Query<(&mut Position, &Position)>) {}
+
+    alias.into_system();
+  }
+
+  #[test]
+  fn queries_can_read_a_component_twice() {
+    let mut world = World::new();
+    world.spawn_bundle((Position(1, 2),));
+    let tick = world.change_tick();
+
+    let pairs: Vec<_> = world.query::<(&Position, &Position)>().iter().map(|(_, (a, b))| a.0 + b.1).collect();
+    assert_eq!(pairs, [3]);
+    let id = world.entities()[0];
+    assert_eq!(world.last_changed::<Position>(id), Some(tick));
+  }
+
+  #[test]
+  fn world_queries_write_back_and_filter() {
+    let mut world = World::new();
+    let moving = world.spawn_bundle((Position(0, 0), Velocity(1, 1)));
+    let labelled = world.spawn_bundle((Position(5, 5), Velocity(0, 1), Label("slow")));
+
+    for (_, (pos, vel)) in world.query::<(&mut Position, &Velocity)>().iter() {
+      pos.0 += vel.0;
+      pos.1 += vel.1;
+    }
+    assert_eq!(world.get::<Position>(moving), Some(&Position(1, 1)));
+    assert_eq!(world.get::<Position>(labelled), Some(&Position(5, 6)));
+    assert!(world.is_changed::<Position>(moving));
+
+    let unlabelled: Vec<EntityId> =
+      world.query_filtered::<&Position, Without<Label>>().iter().map(|(id, _)| id).collect();
+    assert_eq!(unlabelled, [moving]);
+    let labelled_ids: Vec<EntityId> =
+      world.query_filtered::<&Velocity, With<Label>>().iter().map(|(id, _)| id).collect();
+    assert_eq!(labelled_ids, [labelled]);
+  }
+}
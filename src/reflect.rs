@@ -0,0 +1,115 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::fmt;
+
+use crate::{EntityId, HashMap, World};
+
+/// Runtime introspection of a component, e.g. for an editor listing an
+/// entity's components and editing their fields. Usually derived with
+/// `#[derive(Reflect)]`, which needs every field to implement `Clone` and
+/// `Debug`.
+pub trait Reflect: 'static {
+  fn type_name(&self) -> &'static str;
+
+  fn as_any(&self) -> &dyn Any;
+
+  fn clone_reflect(&self) -> Box<dyn Reflect>;
+
+  /// The fields by name, or by position for tuple structs.
+  fn fields(&self) -> Vec<(&'static str, &dyn fmt::Debug)>;
+
+  /// The field called `name`, e.g. `"x"`, or `"0"` for the first field of a
+  /// tuple struct.
+  fn field(&self, name: &str) -> Option<&dyn Any>;
+
+  fn field_mut(&mut self, name: &str) -> Option<&mut dyn Any>;
+}
+
+#[cfg(not(feature = "parallel"))]
+pub type FormatFn = Box<dyn Fn(&dyn Any) -> String>;
+#[cfg(feature = "parallel")]
+pub type FormatFn = Box<dyn Fn(&dyn Any) -> String + Send + Sync>;
+
+pub struct ComponentInfo {
+  pub name: &'static str,
+  /// Prints a value of the component, which has to be of its type.
+  pub format: FormatFn,
+}
+
+/// Prints `value` the way `#[derive(Debug)]` would.
+fn format<T: Reflect>(value: &dyn Any) -> String {
+  struct Fields<'a>(&'a dyn Reflect);
+
+  impl fmt::Debug for Fields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      let name = self.0.type_name().rsplit("::").next().unwrap_or_default();
+      let fields = self.0.fields();
+      if fields.iter().all(|(field, _)| field.parse::<usize>().is_ok()) {
+        let mut tuple = f.debug_tuple(name);
+        for (_, value) in fields {
+          tuple.field(value);
+        }
+        tuple.finish()
+      } else {
+        let mut object = f.debug_struct(name);
+        for (field, value) in fields {
+          object.field(field, value);
+        }
+        object.finish()
+      }
+    }
+  }
+
+  let value = value.downcast_ref::<T>().expect("formatter registered for its own type");
+  format!("{:?}", Fields(value))
+}
+
+/// Maps component types to what tooling needs to know about them. Insert it
+/// as a resource to make the registered components show up in
+/// `World::reflect`.
+#[derive(Default)]
+pub struct ReflectRegistry {
+  infos: HashMap<TypeId, ComponentInfo>,
+}
+
+impl ReflectRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn register<T: Reflect>(&mut self) {
+    self.infos.insert(TypeId::of::<T>(), ComponentInfo {
+      name: core::any::type_name::<T>(),
+      format: Box::new(format::<T>),
+    });
+  }
+
+  pub fn get(&self, ty: TypeId) -> Option<&ComponentInfo> {
+    self.infos.get(&ty)
+  }
+}
+
+impl World {
+  /// The name and printed value of each of `id`'s components registered in
+  /// the `ReflectRegistry` resource, sorted by name. Empty if there is no
+  /// registry.
+  pub fn reflect(&self, id: EntityId) -> Vec<(&'static str, String)> {
+    let Some(registry) = self.get_resource::<ReflectRegistry>() else {
+      return vec![];
+    };
+    let mut components: Vec<_> = self
+      .component_types(id)
+      .into_iter()
+      .filter_map(|ty| {
+        let info = registry.get(ty)?;
+        Some((info.name, (info.format)(self.get_dyn(id, ty)?)))
+      })
+      .collect();
+    components.sort_by_key(|(name, _)| *name);
+    components
+  }
+}
@@ -0,0 +1,123 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::TypeId;
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::{EntityId, World};
+
+/// A typed reference from the entity that has it to another entity, e.g.
+/// `Relationship<Targets>` on an arrow pointing at an enemy. `T` is a tag
+/// that tells kinds of relationship apart. Serializes as its target, so
+/// prefabs remap it like a `Parent`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent, bound = ""))]
+pub struct Relationship<T> {
+  target: EntityId,
+  #[cfg_attr(feature = "serde", serde(skip))]
+  marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Relationship<T> {
+  pub fn new(target: EntityId) -> Self {
+    Self { target, marker: PhantomData }
+  }
+
+  pub fn target(&self) -> EntityId {
+    self.target
+  }
+}
+
+// Derives would require `T` to implement the traits too.
+impl<T> Clone for Relationship<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for Relationship<T> {}
+
+impl<T> PartialEq for Relationship<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.target == other.target
+  }
+}
+
+impl<T> Eq for Relationship<T> {}
+
+impl<T> fmt::Debug for Relationship<T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Relationship<{}>({:?})", core::any::type_name::<T>(), self.target)
+  }
+}
+
+pub(crate) type CascadeFn = fn(&mut World, EntityId);
+
+/// Removes every `Relationship<T>` that points at `target`.
+fn cascade<T: 'static>(world: &mut World, target: EntityId) {
+  let sources: Vec<EntityId> = world
+    .matching_entities(vec![TypeId::of::<Relationship<T>>()])
+    .filter(|&source| world.related::<T>(source) == Some(target))
+    .collect();
+  for source in sources {
+    world.remove_component::<Relationship<T>>(source);
+  }
+}
+
+impl World {
+  /// Points `source` at `target`, replacing its previous `T` relationship.
+  pub fn relate<T: 'static>(&mut self, source: EntityId, target: EntityId) {
+    self.add_component(source, Relationship::<T>::new(target));
+  }
+
+  /// The target of `source`'s `T` relationship. Unless `T` was registered
+  /// with `cascade_on_despawn`, the target may no longer be alive.
+  pub fn related<T: 'static>(&self, source: EntityId) -> Option<EntityId> {
+    self.get::<Relationship<T>>(source).map(Relationship::target)
+  }
+
+  /// Removes every `Relationship<T>` pointing at an entity when it is
+  /// despawned, so that no `T` relationship is left dangling.
+  pub fn cascade_on_despawn<T: 'static>(&mut self) {
+    self.cascades.insert(TypeId::of::<T>(), cascade::<T>);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::World;
+
+  struct Targets;
+
+  struct Follows;
+
+  #[test]
+  fn cascades_remove_relationships_to_despawned_targets() {
+    let mut world = World::new();
+    world.cascade_on_despawn::<Targets>();
+    let enemy = world.spawn();
+    let other = world.spawn();
+    let arrow = world.spawn();
+    let stray = world.spawn();
+    world.relate::<Targets>(arrow, enemy);
+    world.relate::<Targets>(stray, other);
+    world.relate::<Follows>(arrow, enemy);
+
+    world.despawn(enemy);
+    assert_eq!(world.related::<Targets>(arrow), None);
+    assert_eq!(world.related::<Targets>(stray), Some(other));
+    assert_eq!(world.related::<Follows>(arrow), Some(enemy));
+  }
+
+  #[test]
+  fn relationships_without_a_cascade_are_kept() {
+    let mut world = World::new();
+    let enemy = world.spawn();
+    let arrow = world.spawn();
+    world.relate::<Targets>(arrow, enemy);
+
+    world.despawn(enemy);
+    assert_eq!(world.related::<Targets>(arrow), Some(enemy));
+    assert!(!world.is_alive(enemy));
+  }
+}
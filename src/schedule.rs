@@ -0,0 +1,861 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::TypeId;
+use core::error::Error;
+use core::fmt;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use crate::{Condition, Exclusive, HashMap, IntoSystem, System, World};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+  Cycle(Vec<&'static str>),
+  /// Sets nested in one another in a loop.
+  SetCycle(Vec<&'static str>),
+}
+
+impl fmt::Display for ScheduleError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ScheduleError::Cycle(systems) => {
+        write!(f, "systems form a dependency cycle: {}", systems.join(", "))
+      }
+      ScheduleError::SetCycle(sets) => {
+        write!(f, "system sets are nested in a cycle: {}", sets.join(" in "))
+      }
+    }
+  }
+}
+
+impl Error for ScheduleError {}
+
+/// The timing of the `FixedUpdate` stage, available to its systems as a
+/// resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedTime {
+  /// The time every fixed step advances by.
+  pub delta: Duration,
+  /// Frame time not yet consumed by a fixed step.
+  pub accumulator: Duration,
+}
+
+/// How a schedule runs its systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParallelismMode {
+  /// One system at a time, in dependency order.
+  #[default]
+  Sequential,
+  /// Consecutive systems whose access does not conflict run at the same time
+  /// on the rayon thread pool.
+  #[cfg(feature = "parallel")]
+  Rayon,
+}
+
+/// A phase of every `Schedule::run`. Stages run one after the other, each
+/// finishing all of its systems before the next one starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+  PreUpdate,
+  /// Where `add_system` puts systems, preceded by `FixedUpdate`.
+  Update,
+  PostUpdate,
+  Last,
+  /// A stage placed with `add_stage_before` or `add_stage_after`.
+  Custom(&'static str),
+}
+
+const BUILTIN_STAGES: [Stage; 4] = [Stage::PreUpdate, Stage::Update, Stage::PostUpdate, Stage::Last];
+
+/// A named group of systems that share run conditions. Sets can be nested in
+/// other sets, whose conditions then apply to them too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemSet(&'static str);
+
+impl SystemSet {
+  pub fn new(name: &'static str) -> Self {
+    Self(name)
+  }
+
+  pub fn name(&self) -> &'static str {
+    self.0
+  }
+}
+
+impl From<&'static str> for SystemSet {
+  fn from(name: &'static str) -> Self {
+    Self(name)
+  }
+}
+
+#[derive(Default)]
+struct SetConfig {
+  parent: Option<SystemSet>,
+  conditions: Vec<Box<dyn Condition>>,
+}
+
+/// Whether a whole `Schedule::run` goes ahead, as decided by its run
+/// criteria.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShouldRun {
+  Yes,
+  No,
+  /// Runs the schedule, then asks the criteria again, e.g. to step physics
+  /// until it settles.
+  YesAndCheckAgain,
+}
+
+const DEFAULT_FIXED_HZ: f64 = 60.0;
+
+struct Entry {
+  id: TypeId,
+  system: Box<dyn System>,
+  after: Vec<TypeId>,
+  set: Option<SystemSet>,
+  condition: Option<Box<dyn Condition>>,
+}
+
+#[derive(Default)]
+pub struct Schedule {
+  systems: Vec<Entry>,
+  order: Vec<usize>,
+  sets: HashMap<SystemSet, SetConfig>,
+  /// `order` split into runs of systems that can run at the same time.
+  #[cfg(feature = "parallel")]
+  batches: Vec<Vec<usize>>,
+  parallelism: ParallelismMode,
+  fixed_update: Option<Box<Schedule>>,
+  /// Every stage in the order they run; empty until a stage other than
+  /// `Update` is used.
+  stages: Vec<Stage>,
+  /// The stages other than `Update`, whose systems are this schedule's own.
+  stage_schedules: HashMap<Stage, Schedule>,
+  fixed_hz: Option<f64>,
+  #[cfg(feature = "std")]
+  last_run: Option<Instant>,
+  run_criteria: Option<fn(&World) -> ShouldRun>,
+}
+
+impl Schedule {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add_system<M, S>(&mut self, system: S)
+  where
+    S: IntoSystem<M>,
+    S::System: 'static,
+  {
+    self.insert(system, vec![], None, None)
+      .expect("a system without dependencies cannot form a cycle");
+  }
+
+  /// Adds a system that only runs in the frames where the conditions of
+  /// `set`, and of the sets it is nested in, all hold.
+  pub fn add_system_to_set<M, S>(&mut self, system: S, set: impl Into<SystemSet>)
+  where
+    S: IntoSystem<M>,
+    S::System: 'static,
+  {
+    self.insert(system, vec![], Some(set.into()), None)
+      .expect("a system without dependencies cannot form a cycle");
+  }
+
+  /// Adds a system that only runs in the frames where `condition` holds,
+  /// checked before any system of the frame runs.
+  pub fn add_system_if<M, S>(&mut self, condition: impl Condition, system: S)
+  where
+    S: IntoSystem<M>,
+    S::System: 'static,
+  {
+    self.insert(system, vec![], None, Some(Box::new(condition)))
+      .expect("a system without dependencies cannot form a cycle");
+  }
+
+  /// Adds a run condition to `set`. A set with several conditions runs only
+  /// when all of them hold; combine them with `Condition::or` otherwise.
+  pub fn configure_set(&mut self, set: impl Into<SystemSet>, condition: impl Condition) {
+    self.sets.entry(set.into()).or_default().conditions.push(Box::new(condition));
+  }
+
+  /// Nests `set` in `parent`, replacing the set it was nested in before.
+  /// Returns an error, leaving the schedule unchanged, if `parent` is already
+  /// nested in `set`.
+  pub fn add_set_to_set(
+    &mut self,
+    set: impl Into<SystemSet>,
+    parent: impl Into<SystemSet>,
+  ) -> Result<(), ScheduleError> {
+    let (set, parent) = (set.into(), parent.into());
+    let mut chain = vec![set.name()];
+    let mut ancestor = Some(parent);
+    while let Some(current) = ancestor {
+      chain.push(current.name());
+      if current == set {
+        return Err(ScheduleError::SetCycle(chain));
+      }
+      ancestor = self.sets.get(&current).and_then(|config| config.parent);
+    }
+    self.sets.entry(set).or_default().parent = Some(parent);
+    Ok(())
+  }
+
+  /// Adds a system that gets the whole world mutably. The same as passing it
+  /// to `add_system`, which accepts exclusive systems too.
+  pub fn add_exclusive_system<S>(&mut self, system: S)
+  where
+    S: IntoSystem<Exclusive>,
+    S::System: 'static,
+  {
+    self.add_system(system);
+  }
+
+  /// The `FixedUpdate` stage: systems added to it run zero or more times per
+  /// `run`, once for each fixed step that fits into the elapsed frame time.
+  pub fn fixed_update(&mut self) -> &mut Schedule {
+    let parallelism = self.parallelism;
+    self.fixed_update.get_or_insert_with(|| {
+      Box::new(Schedule { parallelism, ..Default::default() })
+    })
+  }
+
+  /// The systems of `stage`, which can be configured like a schedule of
+  /// their own, e.g. with system sets. Panics if `stage` is a custom stage
+  /// that was never added.
+  pub fn stage(&mut self, stage: Stage) -> &mut Schedule {
+    if stage == Stage::Update {
+      return self;
+    }
+    self.init_stages();
+    if !self.stages.contains(&stage) {
+      panic!("stage {:?} was never added to the schedule", stage);
+    }
+    let parallelism = self.parallelism;
+    self.stage_schedules
+      .entry(stage)
+      .or_insert_with(|| Schedule { parallelism, ..Default::default() })
+  }
+
+  pub fn add_system_to_stage<M, S>(&mut self, stage: Stage, system: S)
+  where
+    S: IntoSystem<M>,
+    S::System: 'static,
+  {
+    self.stage(stage).add_system(system);
+  }
+
+  /// Adds the custom `stage` right before `before`. Panics if `stage` was
+  /// already added or `before` was not.
+  pub fn add_stage_before(&mut self, stage: Stage, before: Stage) {
+    let index = self.stage_index(stage, before);
+    self.stages.insert(index, stage);
+  }
+
+  /// Adds the custom `stage` right after `after`. Panics if `stage` was
+  /// already added or `after` was not.
+  pub fn add_stage_after(&mut self, stage: Stage, after: Stage) {
+    let index = self.stage_index(stage, after);
+    self.stages.insert(index + 1, stage);
+  }
+
+  fn stage_index(&mut self, stage: Stage, anchor: Stage) -> usize {
+    self.init_stages();
+    if self.stages.contains(&stage) {
+      panic!("stage {:?} was already added to the schedule", stage);
+    }
+    self.stages
+      .iter()
+      .position(|&other| other == anchor)
+      .unwrap_or_else(|| panic!("stage {:?} was never added to the schedule", anchor))
+  }
+
+  fn init_stages(&mut self) {
+    if self.stages.is_empty() {
+      self.stages = BUILTIN_STAGES.to_vec();
+    }
+  }
+
+  /// Chooses how systems run, `Sequential` by default. Applies to the
+  /// `FixedUpdate` stage and every other stage too.
+  pub fn set_parallelism(&mut self, mode: ParallelismMode) {
+    self.parallelism = mode;
+    if let Some(fixed_update) = &mut self.fixed_update {
+      fixed_update.set_parallelism(mode);
+    }
+    for schedule in self.stage_schedules.values_mut() {
+      schedule.set_parallelism(mode);
+    }
+  }
+
+  /// Sets how many fixed steps per second `FixedUpdate` runs, 60 by default.
+  /// Panics unless `hz` is positive and finite.
+  pub fn set_fixed_hz(&mut self, hz: f64) {
+    assert!(hz.is_finite() && hz > 0.0, "fixed update rate must be positive, got {}", hz);
+    self.fixed_hz = Some(hz);
+  }
+
+  /// Decides before every `run` whether the schedule runs at all, e.g. to
+  /// pause the game while a menu is open. The time of skipped runs does not
+  /// count towards `FixedUpdate` later on.
+  pub fn set_run_criteria(&mut self, criteria: fn(&World) -> ShouldRun) {
+    self.run_criteria = Some(criteria);
+  }
+
+  /// Registers `system` so that it always runs after every instance of
+  /// `dependency` in this schedule. Returns an error, leaving the schedule
+  /// unchanged, if the new ordering would form a cycle.
+  pub fn add_system_after<M, N, S, D>(&mut self, system: S, _dependency: D) -> Result<(), ScheduleError>
+  where
+    S: IntoSystem<M>,
+    S::System: 'static,
+    D: IntoSystem<N>,
+    D::System: 'static,
+  {
+    self.insert(system, vec![TypeId::of::<D::System>()], None, None)
+  }
+
+  fn insert<M, S>(
+    &mut self,
+    system: S,
+    after: Vec<TypeId>,
+    set: Option<SystemSet>,
+    condition: Option<Box<dyn Condition>>,
+  ) -> Result<(), ScheduleError>
+  where
+    S: IntoSystem<M>,
+    S::System: 'static,
+  {
+    self.systems.push(Entry {
+      id: TypeId::of::<S::System>(),
+      system: Box::new(system.into_system()),
+      after,
+      set,
+      condition,
+    });
+    match self.sort() {
+      Ok(order) => {
+        self.order = order;
+        #[cfg(feature = "parallel")]
+        {
+          self.batches = self.batches();
+        }
+        Ok(())
+      }
+      Err(err) => {
+        self.systems.pop();
+        Err(err)
+      }
+    }
+  }
+
+  fn sort(&self) -> Result<Vec<usize>, ScheduleError> {
+    let mut remaining: Vec<usize> = (0..self.systems.len()).collect();
+    let mut order = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+      let ready = remaining.iter().position(|&i| {
+        self.systems[i].after.iter().all(|dependency| {
+          !remaining.iter().any(|&j| self.systems[j].id == *dependency)
+        })
+      });
+      let Some(ready) = ready else {
+        let systems = remaining.iter().map(|&i| self.systems[i].system.name()).collect();
+        return Err(ScheduleError::Cycle(systems));
+      };
+      order.push(remaining.remove(ready));
+    }
+    Ok(order)
+  }
+
+  /// Groups consecutive systems of `order` that neither conflict in their
+  /// access nor depend on one another.
+  #[cfg(feature = "parallel")]
+  fn batches(&self) -> Vec<Vec<usize>> {
+    let mut batches: Vec<Vec<usize>> = vec![];
+    for &i in &self.order {
+      let entry = &self.systems[i];
+      let fits = batches.last().is_some_and(|batch| {
+        batch.iter().all(|&j| {
+          let other = &self.systems[j];
+          !entry.after.contains(&other.id)
+            && entry.system.access().conflict_with(other.system.access()).is_none()
+        })
+      });
+      match batches.last_mut() {
+        Some(batch) if fits => batch.push(i),
+        _ => batches.push(vec![i]),
+      }
+    }
+    batches
+  }
+
+  /// Runs every system once, after as many `FixedUpdate` steps as the time
+  /// since the previous `run` allows.
+  #[cfg(feature = "std")]
+  pub fn run(&mut self, world: &mut World) {
+    let now = Instant::now();
+    let delta = self.last_run.map_or(Duration::ZERO, |last| now - last);
+    self.last_run = Some(now);
+    self.run_for(world, delta);
+  }
+
+  /// Like `run`, but advances the `FixedUpdate` stage by `delta` rather than
+  /// by the time that actually passed. Without the `std` feature, which
+  /// `run` needs for its clock, this is how schedules are run.
+  pub fn run_for(&mut self, world: &mut World, delta: Duration) {
+    let Some(criteria) = self.run_criteria else {
+      return self.run_frame(world, delta);
+    };
+    let mut delta = delta;
+    loop {
+      match criteria(world) {
+        ShouldRun::No => return,
+        ShouldRun::Yes => return self.run_frame(world, delta),
+        ShouldRun::YesAndCheckAgain => {
+          self.run_frame(world, delta);
+          // The elapsed time was consumed by the first run.
+          delta = Duration::ZERO;
+        }
+      }
+    }
+  }
+
+  /// Runs every stage in order, each as a schedule of its own.
+  fn run_frame(&mut self, world: &mut World, delta: Duration) {
+    if self.stages.is_empty() {
+      return self.run_update(world, delta);
+    }
+    for stage in self.stages.clone() {
+      if stage == Stage::Update {
+        self.run_update(world, delta);
+      } else if let Some(schedule) = self.stage_schedules.get_mut(&stage) {
+        schedule.run_for(world, delta);
+      }
+    }
+  }
+
+  fn run_update(&mut self, world: &mut World, delta: Duration) {
+    if let Some(fixed_update) = &mut self.fixed_update {
+      let step = Duration::from_secs_f64(1.0 / self.fixed_hz.unwrap_or(DEFAULT_FIXED_HZ));
+      let mut time = world
+        .get_resource::<FixedTime>()
+        .copied()
+        .unwrap_or(FixedTime { delta: step, accumulator: Duration::ZERO });
+      time.delta = step;
+      time.accumulator += delta;
+      while time.accumulator >= step {
+        time.accumulator -= step;
+        world.insert_resource(time);
+        fixed_update.run_systems(world);
+      }
+      world.insert_resource(time);
+    }
+    self.run_systems(world);
+  }
+
+  /// Runs the systems whose sets are enabled and whose own condition holds,
+  /// with the conditions checked once up front.
+  fn run_systems(&mut self, world: &mut World) {
+    let mut enabled = HashMap::new();
+    let runs: Vec<bool> = self.systems
+      .iter()
+      .map(|entry| {
+        entry.set.is_none_or(|set| self.is_enabled(set, world, &mut enabled))
+          && entry.condition.as_ref().is_none_or(|condition| condition(world))
+      })
+      .collect();
+    match self.parallelism {
+      ParallelismMode::Sequential => {
+        for &i in &self.order {
+          if runs[i] {
+            self.systems[i].system.run(world);
+          }
+        }
+      }
+      #[cfg(feature = "parallel")]
+      ParallelismMode::Rayon => self.run_batches(world, &runs),
+    }
+  }
+
+  fn is_enabled(&self, set: SystemSet, world: &World, enabled: &mut HashMap<SystemSet, bool>) -> bool {
+    if let Some(&known) = enabled.get(&set) {
+      return known;
+    }
+    let result = match self.sets.get(&set) {
+      Some(config) => {
+        config.conditions.iter().all(|condition| condition(world))
+          && config.parent.is_none_or(|parent| self.is_enabled(parent, world, enabled))
+      }
+      None => true,
+    };
+    enabled.insert(set, result);
+    result
+  }
+
+  /// Runs each batch of non-conflicting systems on the rayon thread pool.
+  /// The systems of a batch all see the world as it was before the batch, and
+  /// their commands are applied once every one of them has returned.
+  ///
+  /// Batches are built from each system's access when it is added. A system
+  /// whose parameters conflict with one another panics in `into_system`,
+  /// before it can join a batch.
+  #[cfg(feature = "parallel")]
+  fn run_batches(&mut self, world: &mut World, runs: &[bool]) {
+    for batch in &self.batches {
+      let batch: Vec<usize> = batch.iter().copied().filter(|&i| runs[i]).collect();
+      if let [i] = batch[..] {
+        self.systems[i].system.run(world);
+        continue;
+      }
+      let mut systems: Vec<_> = self.systems
+        .iter_mut()
+        .enumerate()
+        .filter(|(i, _)| batch.contains(i))
+        .map(|(_, entry)| &mut entry.system)
+        .collect();
+      for system in &mut systems {
+        system.take(world);
+      }
+      let shared: &World = world;
+      rayon::scope(|scope| {
+        for system in &mut systems {
+          scope.spawn(move |_| system.call(shared));
+        }
+      });
+      for system in &mut systems {
+        system.finish(world);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use super::*;
+  use crate::ResMut;
+
+  #[test]
+  fn systems_without_dependencies_run_in_insertion_order() {
+    let ran = Arc::new(Mutex::new(vec![]));
+    let mut schedule = Schedule::new();
+    let first = ran.clone();
+    schedule.add_system(move |_: &World| first.lock().unwrap().push("first"));
+    let second = ran.clone();
+    schedule.add_system(move |_: &World| second.lock().unwrap().push("second"));
+
+    schedule.run(&mut World::new());
+
+    assert_eq!(*ran.lock().unwrap(), ["first", "second"]);
+  }
+
+  #[cfg(feature = "parallel")]
+  #[test]
+  fn writers_of_the_same_component_never_overlap() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    use crate::{Query, Res};
+
+    struct Position(i32);
+
+    static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+    static MOST: AtomicUsize = AtomicUsize::new(0);
+
+    fn write(mut query: Query<&mut Position>) {
+      let active = ACTIVE.fetch_add(1, Ordering::SeqCst) + 1;
+      MOST.fetch_max(active, Ordering::SeqCst);
+      for (_, pos) in query.iter() {
+        pos.0 += 1;
+      }
+      thread::sleep(Duration::from_millis(20));
+      ACTIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn first(query: Query<&mut Position>) {
+      write(query);
+    }
+
+    fn second(query: Query<&mut Position>) {
+      write(query);
+    }
+
+    fn read(_: Res<u32>, _: Query<&Position>) {}
+
+    let mut world = World::new();
+    world.insert_resource(0u32);
+    let id = world.spawn_bundle((Position(0),));
+    let mut schedule = Schedule::new();
+    schedule.set_parallelism(ParallelismMode::Rayon);
+    schedule.add_system(first);
+    schedule.add_system(second);
+    schedule.add_system(read);
+    schedule.add_system(|_: Res<u32>| {});
+
+    assert_eq!(schedule.batches, vec![vec![0], vec![1], vec![2, 3]]);
+    schedule.run(&mut world);
+    assert_eq!(MOST.load(Ordering::SeqCst), 1);
+    assert_eq!(world.get::<Position>(id).map(|pos| pos.0), Some(2));
+  }
+
+  #[test]
+  #[should_panic(expected = "has conflicting access")]
+  fn systems_with_conflicting_parameters_are_rejected_when_added() {
+    fn twice(_: ResMut<u32>, _: ResMut<u32>) {}
+
+    Schedule::new().add_system(twice);
+  }
+
+  #[test]
+  fn conditional_systems_run_only_while_their_condition_holds() {
+    struct Ticking;
+
+    fn tick(mut ticks: ResMut<u32>) {
+      *ticks += 1;
+    }
+
+    let mut world = World::new();
+    world.insert_resource(0u32);
+    let mut schedule = Schedule::new();
+    schedule.add_system_if(|world: &World| world.get_resource::<Ticking>().is_some(), tick);
+
+    schedule.run(&mut world);
+    assert_eq!(world.resource::<u32>(), &0);
+    world.insert_resource(Ticking);
+    schedule.run(&mut world);
+    schedule.run(&mut world);
+    assert_eq!(world.resource::<u32>(), &2);
+    world.take_resource::<Ticking>();
+    schedule.run(&mut world);
+    assert_eq!(world.resource::<u32>(), &2);
+  }
+
+  #[derive(Debug, Default)]
+  struct Log(Vec<&'static str>);
+
+  fn first(mut log: ResMut<Log>) {
+    log.0.push("first");
+  }
+
+  fn second(mut log: ResMut<Log>) {
+    log.0.push("second");
+  }
+
+  fn logged(schedule: &mut Schedule) -> Vec<&'static str> {
+    let mut world = World::new();
+    world.insert_resource(Log::default());
+    schedule.run_for(&mut world, Duration::ZERO);
+    world.take_resource::<Log>().unwrap().0
+  }
+
+  #[test]
+  fn systems_run_after_their_dependencies_whatever_the_insertion_order() {
+    let mut schedule = Schedule::new();
+    schedule.add_system_after(second, first).unwrap();
+    schedule.add_system(first);
+
+    assert_eq!(logged(&mut schedule), ["first", "second"]);
+  }
+
+  #[test]
+  fn cycles_are_rejected_without_changing_the_schedule() {
+    let mut schedule = Schedule::new();
+    schedule.add_system_after(second, first).unwrap();
+    schedule.add_system(first);
+    let error = schedule.add_system_after(first, second).unwrap_err();
+
+    assert!(matches!(&error, ScheduleError::Cycle(systems) if systems.len() == 2));
+    assert_eq!(schedule.systems.len(), 2);
+    assert_eq!(logged(&mut schedule), ["first", "second"]);
+  }
+
+  #[test]
+  fn fixed_update_runs_once_per_step_and_carries_the_rest_over() {
+    fn step(mut steps: ResMut<u32>) {
+      *steps += 1;
+    }
+
+    let mut world = World::new();
+    world.insert_resource(0u32);
+    let mut schedule = Schedule::new();
+    schedule.set_fixed_hz(4.0);
+    schedule.fixed_update().add_system(step);
+
+    schedule.run_for(&mut world, Duration::from_millis(600));
+    assert_eq!(world.resource::<u32>(), &2);
+    assert_eq!(world.resource::<FixedTime>().accumulator, Duration::from_millis(100));
+    schedule.run_for(&mut world, Duration::from_millis(100));
+    assert_eq!(world.resource::<u32>(), &2);
+    schedule.run_for(&mut world, Duration::from_millis(100));
+    assert_eq!(world.resource::<u32>(), &3);
+    assert_eq!(
+      world.resource::<FixedTime>(),
+      &FixedTime { delta: Duration::from_millis(250), accumulator: Duration::from_millis(50) },
+    );
+  }
+
+  #[test]
+  fn exclusive_systems_see_earlier_commands_and_run_alone() {
+    use crate::{Commands, Res};
+
+    fn spawn(commands: &mut Commands) {
+      commands.spawn((0u8,));
+    }
+
+    fn count(world: &mut World) {
+      let len = world.len();
+      world.insert_resource(len);
+    }
+
+    fn read(_: Res<u32>) {}
+
+    let mut world = World::new();
+    world.insert_resource(0u32);
+    let mut schedule = Schedule::new();
+    schedule.add_system(read);
+    schedule.add_system(spawn);
+    schedule.add_exclusive_system(count);
+    schedule.add_system(read);
+
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<usize>(), &1);
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<usize>(), &2);
+
+    #[cfg(feature = "parallel")]
+    {
+      schedule.set_parallelism(ParallelismMode::Rayon);
+      assert_eq!(schedule.batches, vec![vec![0, 1], vec![2], vec![3]]);
+      schedule.run_for(&mut world, Duration::ZERO);
+      assert_eq!(world.resource::<usize>(), &3);
+    }
+  }
+
+  #[derive(Debug)]
+  struct Paused;
+
+  fn running(world: &World) -> bool {
+    world.get_resource::<Paused>().is_none()
+  }
+
+  #[test]
+  fn set_conditions_gate_their_systems() {
+    let mut schedule = Schedule::new();
+    schedule.add_system_to_set(first, "gameplay");
+    schedule.add_system(second);
+    schedule.configure_set("gameplay", running);
+    schedule.configure_set("gameplay", |world: &World| world.get_resource::<Log>().is_some());
+
+    let mut world = World::new();
+    world.insert_resource(Log::default());
+    world.insert_resource(Paused);
+    schedule.run_for(&mut world, Duration::ZERO);
+    world.take_resource::<Paused>();
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<Log>().0, ["second", "first", "second"]);
+  }
+
+  #[test]
+  fn nested_sets_inherit_the_conditions_of_their_parents() {
+    let mut schedule = Schedule::new();
+    schedule.add_system_to_set(first, "movement");
+    schedule.add_system_to_set(second, "gameplay");
+    schedule.add_set_to_set("movement", "gameplay").unwrap();
+    schedule.configure_set("gameplay", running);
+
+    let mut world = World::new();
+    world.insert_resource(Log::default());
+    world.insert_resource(Paused);
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert!(world.resource::<Log>().0.is_empty());
+    world.take_resource::<Paused>();
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<Log>().0, ["first", "second"]);
+  }
+
+  #[test]
+  fn sets_cannot_be_nested_in_a_loop() {
+    let mut schedule = Schedule::new();
+    schedule.add_set_to_set("movement", "gameplay").unwrap();
+    schedule.add_set_to_set("gameplay", "game").unwrap();
+
+    assert_eq!(
+      schedule.add_set_to_set("game", "movement"),
+      Err(ScheduleError::SetCycle(vec!["game", "movement", "gameplay", "game"])),
+    );
+    assert_eq!(schedule.add_set_to_set("game", "game"), Err(ScheduleError::SetCycle(vec!["game", "game"])));
+    assert!(!schedule.sets.contains_key(&SystemSet::new("game")));
+    schedule.add_set_to_set("movement", "game").unwrap();
+  }
+
+  #[test]
+  fn run_criteria_skip_or_repeat_whole_runs() {
+    fn settle(world: &World) -> ShouldRun {
+      match world.resource::<Log>().0.len() {
+        0..3 => ShouldRun::YesAndCheckAgain,
+        _ => ShouldRun::No,
+      }
+    }
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(first);
+    schedule.set_run_criteria(settle);
+    let mut world = World::new();
+    world.insert_resource(Log::default());
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<Log>().0.len(), 3);
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<Log>().0.len(), 3);
+
+    schedule.set_run_criteria(|world| match world.get_resource::<Paused>() {
+      Some(_) => ShouldRun::No,
+      None => ShouldRun::Yes,
+    });
+    schedule.run_for(&mut world, Duration::ZERO);
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<Log>().0.len(), 5);
+    world.insert_resource(Paused);
+    schedule.run_for(&mut world, Duration::ZERO);
+    assert_eq!(world.resource::<Log>().0.len(), 5);
+  }
+
+  #[test]
+  fn stages_run_in_order_with_commands_applied_in_between() {
+    use crate::{Commands, Query};
+
+    fn spawn(commands: &mut Commands) {
+      commands.spawn((0u8,));
+    }
+
+    fn count(mut query: Query<&u8>, mut log: ResMut<Log>) {
+      if query.iter().count() == 1 {
+        log.0.push("counted");
+      }
+    }
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(second);
+    schedule.add_system_to_stage(Stage::Last, count);
+    schedule.add_stage_before(Stage::Custom("input"), Stage::Update);
+    schedule.add_stage_after(Stage::Custom("physics"), Stage::Update);
+    schedule.add_system_to_stage(Stage::Custom("physics"), |mut log: ResMut<Log>| log.0.push("physics"));
+    schedule.add_system_to_stage(Stage::Custom("input"), spawn);
+    schedule.add_system_to_stage(Stage::PreUpdate, first);
+
+    assert_eq!(
+      schedule.stages,
+      [Stage::PreUpdate, Stage::Custom("input"), Stage::Update, Stage::Custom("physics"), Stage::PostUpdate, Stage::Last],
+    );
+    assert_eq!(logged(&mut schedule), ["first", "second", "physics", "counted"]);
+  }
+
+  #[test]
+  #[should_panic(expected = "was already added")]
+  fn stages_are_added_once() {
+    let mut schedule = Schedule::new();
+    schedule.add_stage_after(Stage::Custom("physics"), Stage::Update);
+    schedule.add_stage_before(Stage::Custom("physics"), Stage::Last);
+  }
+}
@@ -0,0 +1,272 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{type_name, Any, TypeId};
+use core::error::Error;
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Component, EntityId, HashSet, World};
+
+/// How a registered component type is turned into and back from a `Value`.
+#[derive(Clone, Copy)]
+pub(crate) struct Registration {
+  name: &'static str,
+  serialize: fn(&dyn Any) -> Value,
+  deserialize: fn(Value) -> serde_json::Result<Box<dyn Any>>,
+}
+
+fn serialize<T: Component + Serialize>(component: &dyn Any) -> Value {
+  let component = component.downcast_ref::<T>().expect("registered for its own type");
+  serde_json::to_value(component).unwrap_or_else(|error| {
+    panic!("component `{}` failed to serialize: {}", type_name::<T>(), error)
+  })
+}
+
+fn deserialize<T: Component + DeserializeOwned>(value: Value) -> serde_json::Result<Box<dyn Any>> {
+  Ok(Box::new(serde_json::from_value::<T>(value)?))
+}
+
+/// The entities of a world and their registered components, keyed by the
+/// names they were registered with.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+  pub entities: Vec<EntitySnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+  pub id: EntityId,
+  pub components: BTreeMap<String, Value>,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+  UnknownComponent(String),
+  Deserialize(String, serde_json::Error),
+  /// A diff changes or despawns an entity that is not alive.
+  NoSuchEntity(EntityId),
+  /// A diff spawns an entity whose id is already taken, or a snapshot lists
+  /// the same entity index twice.
+  IdCollision(EntityId),
+}
+
+impl fmt::Display for SnapshotError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SnapshotError::UnknownComponent(name) => {
+        write!(f, "no component was registered as `{}`", name)
+      }
+      SnapshotError::Deserialize(name, error) => {
+        write!(f, "component `{}` failed to deserialize: {}", name, error)
+      }
+      SnapshotError::NoSuchEntity(id) => write!(f, "entity {} does not exist", id),
+      SnapshotError::IdCollision(id) => write!(f, "entity {} is already taken", id),
+    }
+  }
+}
+
+impl Error for SnapshotError {}
+
+impl World {
+  /// Includes `T` in snapshots under `name`, which has to stay the same
+  /// between the builds that save and load them.
+  pub fn register_component<T>(&mut self, name: &'static str)
+  where
+    T: Component + Serialize + DeserializeOwned,
+  {
+    self.register_storage::<T>();
+    self.registered.insert(name, TypeId::of::<T>());
+    self.registrations.insert(TypeId::of::<T>(), Registration {
+      name,
+      serialize: serialize::<T>,
+      deserialize: deserialize::<T>,
+    });
+  }
+
+  /// Captures every entity, in spawn order, along with its registered
+  /// components. Other components are left out.
+  pub fn snapshot(&self) -> WorldSnapshot {
+    let entities = self.entities.iter().map(|&id| self.snapshot_entity(id)).collect();
+    WorldSnapshot { entities }
+  }
+
+  /// `id` with its registered components.
+  pub(crate) fn snapshot_entity(&self, id: EntityId) -> EntitySnapshot {
+    EntitySnapshot {
+      id,
+      components: self.component_types(id)
+        .into_iter()
+        .filter_map(|ty| {
+          let registration = self.registrations.get(&ty)?;
+          let component = self.get_dyn(id, ty)?;
+          Some((registration.name.to_string(), (registration.serialize)(component)))
+        })
+        .collect(),
+    }
+  }
+
+  /// Replaces every entity with the ones in `snapshot`, keeping their ids.
+  /// Resources are left untouched, and so is the world if any component
+  /// fails to load or an entity index appears twice.
+  pub fn restore(&mut self, snapshot: &WorldSnapshot) -> Result<(), SnapshotError> {
+    let mut used = HashSet::new();
+    for entity in &snapshot.entities {
+      if !used.insert(entity.id.index) {
+        return Err(SnapshotError::IdCollision(entity.id));
+      }
+    }
+    let mut components = vec![];
+    for entity in &snapshot.entities {
+      for (name, value) in &entity.components {
+        let (ty, component) = self.load_component(name, value)?;
+        components.push((entity.id, ty, component));
+      }
+    }
+    for id in self.entities.clone() {
+      self.despawn(id);
+    }
+    for entity in &snapshot.entities {
+      let index = entity.id.index as usize;
+      if index >= self.generations.len() {
+        self.generations.resize(index + 1, 0);
+      }
+      self.generations[index] = entity.id.generation;
+      self.push_entity(entity.id);
+      self.archetypes.insert(entity.id);
+    }
+    self.free = (0..self.generations.len() as u32)
+      .rev()
+      .filter(|index| !used.contains(index))
+      .collect();
+    self.deferring_observers(|world| {
+      for (id, ty, component) in components {
+        world.insert_boxed(id, ty, component);
+      }
+    });
+    Ok(())
+  }
+
+  /// The type registered as `name`, with `value` loaded as one and then
+  /// migrated.
+  pub(crate) fn load_component(
+    &self,
+    name: &str,
+    value: &Value,
+  ) -> Result<(TypeId, Box<dyn Any>), SnapshotError> {
+    let ty = self.registered_type(name)?;
+    let component = (self.registrations[&ty].deserialize)(value.clone())
+      .map_err(|error| SnapshotError::Deserialize(name.to_string(), error))?;
+    Ok(self.migrate(ty, component))
+  }
+
+  pub(crate) fn registered_type(&self, name: &str) -> Result<TypeId, SnapshotError> {
+    self.registered
+      .get(name)
+      .copied()
+      .ok_or_else(|| SnapshotError::UnknownComponent(name.to_string()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::query;
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct Position(i32, i32);
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct Label(String);
+
+  fn registered() -> World {
+    let mut world = World::new();
+    world.register_component::<Position>("position");
+    world.register_component::<Label>("label");
+    world
+  }
+
+  fn positions(world: &World) -> Vec<(EntityId, Position, Option<Label>)> {
+    query!(world, Position, ?Label)
+      .map(|(id, pos, label)| (id, pos.clone(), label.cloned()))
+      .collect()
+  }
+
+  #[test]
+  fn snapshots_round_trip_through_json() {
+    let mut world = registered();
+    world.spawn_bundle((Position(0, 0), Label("player".into())));
+    let gone = world.spawn_bundle((Position(1, 1),));
+    world.spawn_bundle((Position(2, 2),));
+    world.despawn(gone);
+    world.spawn_bundle((Position(3, 3), Label("reused".into())));
+
+    let json = serde_json::to_string(&world.snapshot()).unwrap();
+    let snapshot: WorldSnapshot = serde_json::from_str(&json).unwrap();
+    let mut loaded = registered();
+    loaded.restore(&snapshot).unwrap();
+
+    assert_eq!(positions(&loaded), positions(&world));
+    let fresh = loaded.spawn();
+    assert!(world.entities().iter().all(|&id| id.index() != fresh.index()));
+  }
+
+  #[test]
+  fn duplicate_ids_are_rejected_before_anything_changes() {
+    let mut world = registered();
+    let kept = world.spawn_bundle((Position(9, 9),));
+    let mut snapshot = registered().snapshot();
+    let mut source = registered();
+    let id = source.spawn_bundle((Position(0, 0),));
+    snapshot.entities.push(source.snapshot_entity(id));
+    snapshot.entities.push(source.snapshot_entity(id));
+
+    assert!(matches!(world.restore(&snapshot), Err(SnapshotError::IdCollision(dup)) if dup == id));
+    assert_eq!(positions(&world), vec![(kept, Position(9, 9), None)]);
+
+    snapshot.entities.pop();
+    world.restore(&snapshot).unwrap();
+    world.despawn(id);
+    assert!(world.is_empty());
+    assert!(world.archetypes().iter().all(|archetype| archetype.entities().is_empty()));
+  }
+
+  #[test]
+  fn corrupt_components_leave_the_world_untouched() {
+    let mut world = registered();
+    let kept = world.spawn_bundle((Position(9, 9),));
+    let mut snapshot = world.snapshot();
+    snapshot.entities[0].components.insert("position".into(), Value::from("not a position"));
+
+    assert!(matches!(world.restore(&snapshot), Err(SnapshotError::Deserialize(name, _)) if name == "position"));
+    snapshot.entities[0].components.clear();
+    snapshot.entities[0].components.insert("velocity".into(), Value::Null);
+    assert!(matches!(world.restore(&snapshot), Err(SnapshotError::UnknownComponent(name)) if name == "velocity"));
+    assert_eq!(positions(&world), vec![(kept, Position(9, 9), None)]);
+  }
+
+  #[test]
+  fn restoring_rolls_the_world_back() {
+    let mut world = registered();
+    let a = world.spawn_bundle((Position(0, 0),));
+    let b = world.spawn_bundle((Position(5, 5), Label("b".into())));
+    let before = positions(&world);
+    let snapshot = world.snapshot();
+
+    world.update::<Position>(b, |pos| pos.0 = 100);
+    world.remove_component::<Label>(b);
+    world.despawn(a);
+    let late = world.spawn_bundle((Position(7, 7),));
+    world.restore(&snapshot).unwrap();
+
+    assert_eq!(positions(&world), before);
+    assert!(world.is_alive(a));
+    assert!(!world.is_alive(late));
+  }
+}
@@ -0,0 +1,82 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::TypeId;
+use core::fmt;
+
+use crate::{Component, HashMap, World};
+
+/// How many entities there are and how many of them have each component
+/// type, as returned by `World::stats`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WorldStats {
+  pub entities: usize,
+  /// Entity counts keyed by component type name. Types no entity has any
+  /// more are listed with a count of 0.
+  pub components: HashMap<&'static str, usize>,
+}
+
+impl fmt::Display for WorldStats {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} entities", self.entities)?;
+    let mut components: Vec<_> = self.components.iter().collect();
+    components.sort();
+    for (name, count) in components {
+      write!(f, "\n  {}: {}", name, count)?;
+    }
+    Ok(())
+  }
+}
+
+impl World {
+  pub fn entity_count(&self) -> usize {
+    self.len()
+  }
+
+  /// How many entities have a `T`.
+  pub fn component_count<T: Component>(&self) -> usize {
+    self.matching_entities(vec![TypeId::of::<T>()]).count()
+  }
+
+  pub fn stats(&self) -> WorldStats {
+    let components = self.archetypes
+      .names()
+      .map(|(ty, name)| (name, self.matching_entities(vec![ty]).count()))
+      .collect();
+    WorldStats { entities: self.len(), components }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use core::any::type_name;
+
+  use crate::World;
+
+  #[derive(Debug, PartialEq)]
+  struct Position(i32);
+
+  #[derive(Debug, PartialEq)]
+  struct Health(u32);
+
+  #[test]
+  fn stats_count_entities_per_component() {
+    let mut world = World::new();
+    let a = world.spawn_bundle((Position(0), Health(3)));
+    let b = world.spawn_bundle((Position(1),));
+    assert_eq!(world.entity_count(), 2);
+    assert_eq!(world.get::<Health>(a), Some(&Health(3)));
+    assert_eq!(world.component_count::<Position>(), 2);
+    assert_eq!(world.component_count::<Health>(), 1);
+
+    world.despawn(a);
+    let stats = world.stats();
+    assert_eq!(stats.entities, 1);
+    assert_eq!(stats.components[type_name::<Position>()], 1);
+    assert_eq!(stats.components[type_name::<Health>()], 0);
+    assert_eq!(world.get::<Position>(b), Some(&Position(1)));
+    assert_eq!(
+      stats.to_string(),
+      format!("1 entities\n  {}: 0\n  {}: 1", type_name::<Health>(), type_name::<Position>()),
+    );
+  }
+}
@@ -0,0 +1,297 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{type_name, Any};
+use core::mem;
+
+use crate::{Component, EntityId, HashMap, HashSet, MaybeSync};
+
+/// Where the values of a component type are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageKind {
+  /// One contiguous column per archetype, so iterating a query walks
+  /// densely packed values.
+  #[default]
+  Table,
+  /// A single `SparseSet` per component type, kept out of the archetypes.
+  /// Suits components that few entities have or that are added and removed
+  /// often, since they neither fragment archetypes nor move entities
+  /// between them.
+  SparseSet,
+}
+
+/// A type-erased `Vec<T>` holding one archetype's values of a component.
+/// Row `i` belongs to the archetype's `i`th entity.
+pub trait Column: MaybeSync {
+  /// A new, empty column of the same component type.
+  fn empty(&self) -> Box<dyn Column>;
+
+  fn as_any_mut(&mut self) -> &mut dyn Any;
+
+  fn get(&self, row: usize) -> &dyn Any;
+
+  fn get_mut(&mut self, row: usize) -> &mut dyn Any;
+
+  fn iter_mut(&mut self) -> Box<dyn Iterator<Item = &mut dyn Any> + '_>;
+
+  fn push(&mut self, value: Box<dyn Any>);
+
+  /// Returns the value that was at `row`.
+  fn replace(&mut self, row: usize, value: Box<dyn Any>) -> Box<dyn Any>;
+
+  fn swap_remove(&mut self, row: usize) -> Box<dyn Any>;
+
+  /// Drops every value, keeping the allocation.
+  fn clear(&mut self);
+
+  /// Moves the value at `row` to the end of `other`, which must hold the same
+  /// component type.
+  fn move_row(&mut self, row: usize, other: &mut dyn Column);
+}
+
+/// The `Column` of a single component type.
+struct Values<T>(Vec<T>);
+
+pub(crate) fn new_column<T: Component>() -> Box<dyn Column> {
+  Box::new(Values::<T>(vec![]))
+}
+
+/// Pushes `value` to `column`, which has to hold `T`s, without boxing it.
+pub(crate) fn push<T: Component>(column: &mut dyn Column, value: T) {
+  column
+    .as_any_mut()
+    .downcast_mut::<Values<T>>()
+    .expect("columns of the same component type")
+    .0
+    .push(value);
+}
+
+fn unbox<T: 'static>(value: Box<dyn Any>) -> T {
+  match value.downcast::<T>() {
+    Ok(value) => *value,
+    Err(_) => panic!("value stored in a column of `{}` has the wrong type", type_name::<T>()),
+  }
+}
+
+impl<T: Component> Column for Values<T> {
+  fn empty(&self) -> Box<dyn Column> {
+    new_column::<T>()
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn get(&self, row: usize) -> &dyn Any {
+    &self.0[row]
+  }
+
+  fn get_mut(&mut self, row: usize) -> &mut dyn Any {
+    &mut self.0[row]
+  }
+
+  fn iter_mut(&mut self) -> Box<dyn Iterator<Item = &mut dyn Any> + '_> {
+    Box::new(self.0.iter_mut().map(|value| value as &mut dyn Any))
+  }
+
+  fn push(&mut self, value: Box<dyn Any>) {
+    self.0.push(unbox::<T>(value));
+  }
+
+  fn replace(&mut self, row: usize, value: Box<dyn Any>) -> Box<dyn Any> {
+    Box::new(core::mem::replace(&mut self.0[row], unbox::<T>(value)))
+  }
+
+  fn swap_remove(&mut self, row: usize) -> Box<dyn Any> {
+    Box::new(self.0.swap_remove(row))
+  }
+
+  fn clear(&mut self) {
+    self.0.clear();
+  }
+
+  fn move_row(&mut self, row: usize, other: &mut dyn Column) {
+    let other = other
+      .as_any_mut()
+      .downcast_mut::<Values<T>>()
+      .expect("columns of the same component type");
+    other.0.push(self.0.swap_remove(row));
+  }
+}
+
+/// The values of one component type for the entities that have it, packed
+/// densely and indexed by entity index.
+pub struct SparseSet {
+  sparse: Vec<Option<usize>>,
+  entities: Vec<EntityId>,
+  values: Box<dyn Column>,
+}
+
+impl SparseSet {
+  pub(crate) fn new(values: Box<dyn Column>) -> Self {
+    Self { sparse: vec![], entities: vec![], values }
+  }
+
+  /// A new, empty set of the same component type.
+  pub(crate) fn empty(&self) -> Self {
+    Self::new(self.values.empty())
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.entities.len()
+  }
+
+  pub(crate) fn entities(&self) -> &[EntityId] {
+    &self.entities
+  }
+
+  fn row(&self, id: EntityId) -> Option<usize> {
+    let row = (*self.sparse.get(id.index as usize)?)?;
+    (self.entities[row] == id).then_some(row)
+  }
+
+  pub(crate) fn contains(&self, id: EntityId) -> bool {
+    self.row(id).is_some()
+  }
+
+  pub(crate) fn get(&self, id: EntityId) -> Option<&dyn Any> {
+    Some(self.values.get(self.row(id)?))
+  }
+
+  pub(crate) fn get_mut(&mut self, id: EntityId) -> Option<&mut dyn Any> {
+    let row = self.row(id)?;
+    Some(self.values.get_mut(row))
+  }
+
+  /// Returns the value `id` had before, if any.
+  pub(crate) fn insert(&mut self, id: EntityId, value: Box<dyn Any>) -> Option<Box<dyn Any>> {
+    if let Some(row) = self.row(id) {
+      return Some(self.values.replace(row, value));
+    }
+    let index = id.index as usize;
+    if index >= self.sparse.len() {
+      self.sparse.resize(index + 1, None);
+    }
+    self.sparse[index] = Some(self.entities.len());
+    self.entities.push(id);
+    self.values.push(value);
+    None
+  }
+
+  pub(crate) fn remove(&mut self, id: EntityId) -> Option<Box<dyn Any>> {
+    let row = self.row(id)?;
+    self.sparse[id.index as usize] = None;
+    self.entities.swap_remove(row);
+    if let Some(moved) = self.entities.get(row) {
+      self.sparse[moved.index as usize] = Some(row);
+    }
+    Some(self.values.swap_remove(row))
+  }
+
+  pub(crate) fn clear(&mut self) {
+    self.sparse.clear();
+    self.entities.clear();
+    self.values.clear();
+  }
+
+  pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut dyn Any)> {
+    self.entities.iter().copied().zip(self.values.iter_mut())
+  }
+}
+
+/// The tick at which each entity last changed one component type. While
+/// the type has observers, values borrowed mutably in place are remembered
+/// too, until the observers have seen them.
+#[derive(Clone, Default)]
+pub struct Ticks {
+  ticks: HashMap<EntityId, u32>,
+  observed: bool,
+  mutated: HashSet<EntityId>,
+}
+
+impl Ticks {
+  /// Records that `id`'s value was set at `tick`.
+  pub(crate) fn insert(&mut self, id: EntityId, tick: u32) {
+    self.ticks.insert(id, tick);
+  }
+
+  /// Records that `id`'s value was borrowed mutably at `tick`.
+  pub(crate) fn borrow_mut(&mut self, id: EntityId, tick: u32) {
+    self.ticks.insert(id, tick);
+    if self.observed {
+      self.mutated.insert(id);
+    }
+  }
+
+  pub(crate) fn get(&self, id: EntityId) -> Option<u32> {
+    self.ticks.get(&id).copied()
+  }
+
+  pub(crate) fn remove(&mut self, id: EntityId) {
+    self.ticks.remove(&id);
+    self.mutated.remove(&id);
+  }
+
+  pub(crate) fn clear(&mut self) {
+    self.ticks.clear();
+    self.mutated.clear();
+  }
+
+  pub(crate) fn set_observed(&mut self, observed: bool) {
+    self.observed = observed;
+    if !observed {
+      self.mutated.clear();
+    }
+  }
+
+  /// Forgets that `id`'s value was borrowed mutably, once observers saw it.
+  pub(crate) fn seen(&mut self, id: EntityId) {
+    self.mutated.remove(&id);
+  }
+
+  /// The entities whose value was borrowed mutably since observers last
+  /// saw it.
+  pub(crate) fn take_mutated(&mut self) -> HashSet<EntityId> {
+    mem::take(&mut self.mutated)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{query, query_mut, EntityId, StorageKind, World};
+
+  #[derive(Debug, PartialEq)]
+  struct Position(i32, i32);
+
+  #[derive(Debug, PartialEq)]
+  struct Stunned(u32);
+
+  #[test]
+  fn sparse_components_stay_out_of_the_archetypes() {
+    let mut world = World::new();
+    world.set_storage::<Stunned>(StorageKind::SparseSet);
+    let ids = world.spawn_batch((0..10).map(|i| (Position(i, i),)));
+    let archetypes = world.archetypes().len();
+
+    world.add_component(ids[3], Stunned(2));
+    world.add_component(ids[7], Stunned(5));
+
+    assert_eq!(world.archetypes().len(), archetypes);
+    let stunned: Vec<EntityId> = query!(world, Position, Stunned).map(|(id, ..)| id).collect();
+    assert_eq!(stunned, vec![ids[3], ids[7]]);
+    for (_, stunned) in query_mut!(world, Stunned) {
+      stunned.0 -= 1;
+    }
+    assert_eq!(world.get::<Stunned>(ids[7]), Some(&Stunned(4)));
+    assert_eq!(world.remove_component::<Stunned>(ids[3]), Some(Stunned(1)));
+    assert_eq!(query!(world, Stunned).count(), 1);
+  }
+
+  #[test]
+  #[should_panic(expected = "cannot change once an entity has it")]
+  fn storage_cannot_change_while_in_use() {
+    let mut world = World::new();
+    world.spawn_bundle((Stunned(1),));
+    world.set_storage::<Stunned>(StorageKind::SparseSet);
+  }
+}
@@ -0,0 +1,490 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::{type_name, TypeId};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use crate::{Commands, MaybeSend, Resource, World};
+
+#[derive(Debug, Clone, Default)]
+pub struct Access {
+  reads: Vec<(TypeId, &'static str)>,
+  writes: Vec<(TypeId, &'static str)>,
+  world: bool,
+  exclusive: bool,
+}
+
+impl Access {
+  pub fn add_read<T: 'static>(&mut self) {
+    self.reads.push((TypeId::of::<T>(), type_name::<T>()));
+  }
+
+  pub fn add_write<T: 'static>(&mut self) {
+    self.writes.push((TypeId::of::<T>(), type_name::<T>()));
+  }
+
+  pub fn read_world(&mut self) {
+    self.world = true;
+  }
+
+  /// Marks the access as needing the whole world mutably, which conflicts
+  /// with every other access.
+  pub fn write_world(&mut self) {
+    self.exclusive = true;
+  }
+
+  /// Returns the name of a type that is written by one access and read or
+  /// written by the other, if there is one.
+  pub fn conflict_with(&self, other: &Access) -> Option<&'static str> {
+    if self.exclusive || other.exclusive {
+      return Some("World");
+    }
+    if (self.world && !other.writes.is_empty()) || (other.world && !self.writes.is_empty()) {
+      return Some("World");
+    }
+    let overlaps = |writes: &[(TypeId, &'static str)], accessed: &Access| {
+      writes
+        .iter()
+        .find(|(ty, _)| accessed.reads.iter().chain(&accessed.writes).any(|(other, _)| other == ty))
+        .map(|(_, name)| *name)
+    };
+    overlaps(&self.writes, other).or_else(|| overlaps(&other.writes, self))
+  }
+
+  pub(crate) fn extend(&mut self, other: Access) {
+    self.reads.extend(other.reads);
+    self.writes.extend(other.writes);
+    self.world |= other.world;
+    self.exclusive |= other.exclusive;
+  }
+}
+
+/// A value a system can take as an argument.
+///
+/// Mutable data is moved out of the world by `take` for the duration of the
+/// system and handed back by `restore`, so the remaining parameters can keep
+/// borrowing the world immutably. `apply` runs once every parameter has been
+/// restored and is where deferred work such as `Commands` is flushed.
+///
+/// `State` is kept by the system from one run to the next, e.g. the value of
+/// a `Local`.
+pub trait SystemParam {
+  type State: Default + MaybeSend;
+  type Fetch: MaybeSend;
+  type Item<'f>;
+
+  fn access(access: &mut Access);
+
+  fn take(state: &mut Self::State, world: &mut World) -> Self::Fetch;
+
+  fn get<'f>(fetch: &'f mut Self::Fetch, world: &'f World) -> Self::Item<'f>;
+
+  fn restore(_fetch: &mut Self::Fetch, _world: &mut World) {}
+
+  fn apply(_fetch: Self::Fetch, _state: &mut Self::State, _world: &mut World) {}
+}
+
+pub type SystemParamItem<'f, P> = <P as SystemParam>::Item<'f>;
+
+impl SystemParam for &World {
+  type State = ();
+  type Fetch = ();
+  type Item<'f> = &'f World;
+
+  fn access(access: &mut Access) {
+    access.read_world();
+  }
+
+  fn take(_state: &mut (), _world: &mut World) {}
+
+  fn get<'f>(_fetch: &'f mut (), world: &'f World) -> &'f World {
+    world
+  }
+}
+
+impl SystemParam for &mut Commands {
+  type State = ();
+  type Fetch = Commands;
+  type Item<'f> = &'f mut Commands;
+
+  fn access(_access: &mut Access) {}
+
+  fn take(_state: &mut (), _world: &mut World) -> Commands {
+    Commands::new()
+  }
+
+  fn get<'f>(fetch: &'f mut Commands, _world: &'f World) -> &'f mut Commands {
+    fetch
+  }
+
+  fn apply(fetch: Commands, _state: &mut (), world: &mut World) {
+    world.apply_commands(fetch);
+  }
+}
+
+pub struct Res<'f, T> {
+  value: &'f T,
+}
+
+impl<T> Deref for Res<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    self.value
+  }
+}
+
+impl<T: Resource> SystemParam for Res<'_, T> {
+  type State = ();
+  type Fetch = ();
+  type Item<'f> = Res<'f, T>;
+
+  fn access(access: &mut Access) {
+    access.add_read::<T>();
+  }
+
+  fn take(_state: &mut (), _world: &mut World) {}
+
+  fn get<'f>(_fetch: &'f mut (), world: &'f World) -> Res<'f, T> {
+    Res { value: world.resource::<T>() }
+  }
+}
+
+pub struct ResMut<'f, T> {
+  value: &'f mut T,
+}
+
+impl<T> Deref for ResMut<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    self.value
+  }
+}
+
+impl<T> DerefMut for ResMut<'_, T> {
+  fn deref_mut(&mut self) -> &mut T {
+    self.value
+  }
+}
+
+impl<T: Resource> SystemParam for ResMut<'_, T> {
+  type State = ();
+  type Fetch = Box<T>;
+  type Item<'f> = ResMut<'f, T>;
+
+  fn access(access: &mut Access) {
+    access.add_write::<T>();
+  }
+
+  fn take(_state: &mut (), world: &mut World) -> Box<T> {
+    world.take_resource::<T>().unwrap_or_else(|| {
+      panic!("resource `{}` does not exist", type_name::<T>())
+    })
+  }
+
+  fn get<'f>(fetch: &'f mut Box<T>, _world: &'f World) -> ResMut<'f, T> {
+    ResMut { value: fetch }
+  }
+
+  fn apply(fetch: Box<T>, _state: &mut (), world: &mut World) {
+    world.resources.insert(TypeId::of::<T>(), fetch);
+  }
+}
+
+/// A value owned by a single system that persists between its runs in a
+/// `Schedule`, starting out as `T::default()`, e.g. a frame counter or a
+/// debounce timer. Two systems asking for the same `Local<T>` each get their
+/// own. `World::run_system` starts from the default every time.
+pub struct Local<'f, T> {
+  value: &'f mut T,
+}
+
+impl<T> Deref for Local<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    self.value
+  }
+}
+
+impl<T> DerefMut for Local<'_, T> {
+  fn deref_mut(&mut self) -> &mut T {
+    self.value
+  }
+}
+
+impl<T: Default + MaybeSend + 'static> SystemParam for Local<'_, T> {
+  type State = T;
+  type Fetch = T;
+  type Item<'f> = Local<'f, T>;
+
+  fn access(_access: &mut Access) {}
+
+  fn take(state: &mut T, _world: &mut World) -> T {
+    core::mem::take(state)
+  }
+
+  fn get<'f>(fetch: &'f mut T, _world: &'f World) -> Local<'f, T> {
+    Local { value: fetch }
+  }
+
+  fn apply(fetch: T, state: &mut T, _world: &mut World) {
+    *state = fetch;
+  }
+}
+
+/// Running a system is split into three steps so that a schedule can take
+/// the parameters of several systems before calling them all at once.
+pub trait System: MaybeSend {
+  fn name(&self) -> &'static str;
+
+  fn access(&self) -> &Access;
+
+  /// Takes the system's parameters out of `world`.
+  fn take(&mut self, world: &mut World);
+
+  /// Calls the system with the parameters from the last `take`.
+  fn call(&mut self, world: &World);
+
+  /// Hands the parameters back to `world` and applies their deferred work.
+  fn finish(&mut self, world: &mut World);
+
+  fn run(&mut self, world: &mut World) {
+    self.take(world);
+    self.call(world);
+    self.finish(world);
+  }
+}
+
+/// The parameter states and fetches of a function system, keyed by its `fn`
+/// signature.
+pub trait SystemParams {
+  type States: Default + MaybeSend;
+  type Fetches: MaybeSend;
+}
+
+pub trait IntoSystem<Marker> {
+  type System: System;
+
+  fn into_system(self) -> Self::System;
+}
+
+pub struct FunctionSystem<F, Marker: SystemParams> {
+  func: F,
+  access: Access,
+  states: Marker::States,
+  fetches: Option<Marker::Fetches>,
+  marker: PhantomData<fn() -> Marker>,
+}
+
+macro_rules! impl_function_system {
+  ($($param:ident $state:ident),*) => {
+    impl<$($param: SystemParam),*> SystemParams for fn($($param),*) {
+      type States = ($($param::State,)*);
+      type Fetches = ($($param::Fetch,)*);
+    }
+
+    impl<F, $($param: SystemParam + 'static),*> IntoSystem<fn($($param),*)> for F
+    where
+      F: MaybeSend,
+      for<'a> &'a mut F: FnMut($($param),*) + FnMut($(SystemParamItem<'_, $param>),*),
+    {
+      type System = FunctionSystem<F, fn($($param),*)>;
+
+      fn into_system(self) -> Self::System {
+        let mut access = Access::default();
+        $(
+          let mut param = Access::default();
+          $param::access(&mut param);
+          if let Some(name) = access.conflict_with(&param) {
+            panic!("system `{}` has conflicting access to `{}`", type_name::<F>(), name);
+          }
+          access.extend(param);
+        )*
+        FunctionSystem {
+          func: self,
+          access,
+          states: Default::default(),
+          fetches: None,
+          marker: PhantomData,
+        }
+      }
+    }
+
+    impl<F, $($param: SystemParam + 'static),*> System for FunctionSystem<F, fn($($param),*)>
+    where
+      F: MaybeSend,
+      for<'a> &'a mut F: FnMut($($param),*) + FnMut($(SystemParamItem<'_, $param>),*),
+    {
+      fn name(&self) -> &'static str {
+        type_name::<F>()
+      }
+
+      fn access(&self) -> &Access {
+        &self.access
+      }
+
+      fn take(&mut self, world: &mut World) {
+        let ($($state,)*) = &mut self.states;
+        self.fetches = Some(($($param::take($state, world),)*));
+      }
+
+      #[allow(non_snake_case)]
+      fn call(&mut self, world: &World) {
+        fn call<$($param),*>(mut func: impl FnMut($($param),*), $($param: $param),*) {
+          func($($param),*)
+        }
+
+        let ($($param,)*) = self.fetches.as_mut().expect("parameters are taken before the call");
+        call(&mut self.func, $($param::get($param, world)),*);
+      }
+
+      #[allow(non_snake_case)]
+      fn finish(&mut self, world: &mut World) {
+        let ($(mut $param,)*) = self.fetches.take().expect("parameters are taken before finishing");
+        $($param::restore(&mut $param, world);)*
+        let ($($state,)*) = &mut self.states;
+        $($param::apply($param, $state, world);)*
+      }
+    }
+  };
+}
+
+impl_function_system!(P1 s1);
+impl_function_system!(P1 s1, P2 s2);
+impl_function_system!(P1 s1, P2 s2, P3 s3);
+impl_function_system!(P1 s1, P2 s2, P3 s3, P4 s4);
+impl_function_system!(P1 s1, P2 s2, P3 s3, P4 s4, P5 s5);
+impl_function_system!(P1 s1, P2 s2, P3 s3, P4 s4, P5 s5, P6 s6);
+
+/// The marker of `ExclusiveSystem`s in `IntoSystem`.
+pub struct Exclusive;
+
+/// A system that takes `&mut World` and can therefore do anything, such as
+/// spawning entities based on what a query finds. It never runs alongside
+/// another system.
+pub struct ExclusiveSystem<F> {
+  func: F,
+  access: Access,
+}
+
+impl<F> IntoSystem<Exclusive> for F
+where
+  F: FnMut(&mut World) + MaybeSend,
+{
+  type System = ExclusiveSystem<F>;
+
+  fn into_system(self) -> Self::System {
+    let mut access = Access::default();
+    access.write_world();
+    ExclusiveSystem { func: self, access }
+  }
+}
+
+impl<F> System for ExclusiveSystem<F>
+where
+  F: FnMut(&mut World) + MaybeSend,
+{
+  fn name(&self) -> &'static str {
+    type_name::<F>()
+  }
+
+  fn access(&self) -> &Access {
+    &self.access
+  }
+
+  fn take(&mut self, _world: &mut World) {}
+
+  fn call(&mut self, _world: &World) {}
+
+  /// Exclusive systems do all of their work here, where the world can be
+  /// borrowed mutably.
+  fn finish(&mut self, world: &mut World) {
+    (self.func)(world);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Query, Schedule};
+
+  #[derive(Debug, PartialEq)]
+  struct Position(i32);
+
+  struct Velocity;
+
+  fn access(adds: &[fn(&mut Access)]) -> Access {
+    let mut access = Access::default();
+    for add in adds {
+      add(&mut access);
+    }
+    access
+  }
+
+  #[test]
+  fn only_writes_conflict() {
+    let read = access(&[Access::add_read::<Position>]);
+    let write = access(&[Access::add_write::<Position>]);
+    let other = access(&[Access::add_write::<Velocity>]);
+
+    assert_eq!(read.conflict_with(&read), None);
+    assert!(read.conflict_with(&write).unwrap().ends_with("Position"));
+    assert!(write.conflict_with(&write).unwrap().ends_with("Position"));
+    assert_eq!(write.conflict_with(&other), None);
+  }
+
+  #[test]
+  fn whole_world_access_conflicts_with_writes() {
+    let world = access(&[Access::read_world]);
+    let exclusive = access(&[Access::write_world]);
+    let read = access(&[Access::add_read::<Position>]);
+    let write = access(&[Access::add_write::<Position>]);
+
+    assert_eq!(world.conflict_with(&read), None);
+    assert_eq!(world.conflict_with(&write), Some("World"));
+    assert_eq!(exclusive.conflict_with(&Access::default()), Some("World"));
+  }
+
+  #[derive(Debug, Default, PartialEq)]
+  struct Total(i32);
+
+  fn sum(mut query: Query<&Position>, mut total: ResMut<Total>) {
+    total.0 = query.iter().map(|(_, position)| position.0).sum();
+  }
+
+  fn spawn_marker(total: Res<Total>, commands: &mut Commands) {
+    commands.spawn((Position(total.0),));
+  }
+
+  #[test]
+  fn function_parameters_are_fetched_from_the_world() {
+    let mut world = World::new();
+    world.insert_resource(Total::default());
+    world.spawn_bundle((Position(1),));
+    world.spawn_bundle((Position(2),));
+    let mut schedule = Schedule::new();
+    schedule.add_system(sum);
+    schedule.add_system(spawn_marker);
+    schedule.run(&mut world);
+    assert_eq!(world.resource::<Total>(), &Total(3));
+    assert_eq!(world.query::<&Position>().iter().count(), 3);
+    schedule.run(&mut world);
+    assert_eq!(world.resource::<Total>(), &Total(6));
+  }
+
+  #[test]
+  fn locals_persist_between_runs_of_a_system() {
+    fn count(mut runs: Local<i32>, mut total: ResMut<Total>) {
+      *runs += 1;
+      total.0 = *runs;
+    }
+    let mut world = World::new();
+    world.insert_resource(Total::default());
+    let mut system = count.into_system();
+    system.run(&mut world);
+    system.run(&mut world);
+    assert_eq!(world.resource::<Total>(), &Total(2));
+  }
+}
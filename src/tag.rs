@@ -0,0 +1,120 @@
+use core::any::TypeId;
+use core::mem::size_of;
+
+use crate::{EntityId, HashMap, HashSet, World};
+
+/// A zero-sized marker such as `struct Enemy;` that `World::tag` keeps in a
+/// set of entities per type instead of a component column. Filter queries on
+/// it with `query!(world, Position, Tag<Enemy>)` or `Query<&Position,
+/// Tagged<Enemy>>`. Tags are not part of snapshots.
+pub trait Tag: 'static {}
+
+pub(crate) type TagStorage = HashMap<TypeId, HashSet<EntityId>>;
+
+impl World {
+  /// Tags `id` with `T`, returning whether it was not tagged with it yet.
+  /// Does nothing for entities that are not alive.
+  pub fn tag<T: Tag>(&mut self, id: EntityId) -> bool {
+    const { assert!(size_of::<T>() == 0, "tags have to be zero-sized") };
+    self.is_alive(id) && self.tags.entry(TypeId::of::<T>()).or_default().insert(id)
+  }
+
+  /// Removes the `T` tag from `id`, returning whether it had it.
+  pub fn untag<T: Tag>(&mut self, id: EntityId) -> bool {
+    self.tags.get_mut(&TypeId::of::<T>()).is_some_and(|tagged| tagged.remove(&id))
+  }
+
+  pub fn has_tag<T: Tag>(&self, id: EntityId) -> bool {
+    self.tags.get(&TypeId::of::<T>()).is_some_and(|tagged| tagged.contains(&id))
+  }
+
+  /// How many entities are tagged with `T`.
+  pub fn tag_count<T: Tag>(&self) -> usize {
+    self.tags.get(&TypeId::of::<T>()).map_or(0, |tagged| tagged.len())
+  }
+
+  pub(crate) fn untag_all(&mut self, id: EntityId) {
+    for tagged in self.tags.values_mut() {
+      tagged.remove(&id);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{query, query_count, EntityId, Query, ResMut, Tag, Tagged, World};
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Position(i32, i32);
+
+  struct Enemy;
+
+  impl Tag for Enemy {}
+
+  struct Frozen;
+
+  impl Tag for Frozen {}
+
+  #[test]
+  fn tags_are_set_and_cleared_per_entity() {
+    let mut world = World::new();
+    let id = world.spawn();
+
+    assert!(world.tag::<Enemy>(id));
+    assert!(!world.tag::<Enemy>(id));
+    assert!(world.has_tag::<Enemy>(id));
+    assert!(!world.has_tag::<Frozen>(id));
+    assert_eq!(world.component_types(id).len(), 0);
+    assert!(world.untag::<Enemy>(id));
+    assert!(!world.untag::<Enemy>(id));
+    assert!(!world.has_tag::<Enemy>(id));
+  }
+
+  #[test]
+  fn despawned_entities_lose_their_tags() {
+    let mut world = World::new();
+    let id = world.spawn();
+    world.tag::<Enemy>(id);
+    world.despawn(id);
+
+    assert!(!world.has_tag::<Enemy>(id));
+    assert_eq!(world.tag_count::<Enemy>(), 0);
+    assert!(!world.tag::<Enemy>(id));
+  }
+
+  #[test]
+  fn queries_filter_on_tags() {
+    let mut world = World::new();
+    let enemy = world.spawn_bundle((Position(0, 0),));
+    let frozen_enemy = world.spawn_bundle((Position(1, 1),));
+    world.spawn_bundle((Position(2, 2),));
+    world.tag::<Enemy>(enemy);
+    world.tag::<Enemy>(frozen_enemy);
+    world.tag::<Frozen>(frozen_enemy);
+
+    let enemies: Vec<EntityId> = query!(world, Position, Tag<Enemy>).map(|(id, _)| id).collect();
+    assert_eq!(enemies, [enemy, frozen_enemy]);
+    let frozen: Vec<EntityId> = query!(world, Tag<Frozen>, Position, Tag<Enemy>).map(|(id, _)| id).collect();
+    assert_eq!(frozen, [frozen_enemy]);
+    assert_eq!(query_count!(world, Tag<Enemy>), 2);
+
+    let mut positions = world.query_filtered::<&Position, Tagged<Frozen>>();
+    let positions: Vec<Position> = positions.iter().map(|(_, pos)| pos.clone()).collect();
+    assert_eq!(positions, [Position(1, 1)]);
+  }
+
+  #[test]
+  fn systems_filter_on_tags() {
+    fn count(mut query: Query<&Position, Tagged<Enemy>>, mut total: ResMut<usize>) {
+      *total = query.iter().count();
+    }
+    let mut world = World::new();
+    world.insert_resource(0usize);
+    let id = world.spawn_bundle((Position(0, 0),));
+    world.spawn_bundle((Position(1, 1),));
+    world.tag::<Enemy>(id);
+    world.run_system(count);
+
+    assert_eq!(world.resource::<usize>(), &1);
+  }
+}
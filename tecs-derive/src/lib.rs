@@ -0,0 +1,169 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, GenericArgument, PathArguments, Type};
+
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  let Data::Struct(data) = &input.data else {
+    return syn::Error::new_spanned(&input, "`Bundle` can only be derived for structs")
+      .to_compile_error()
+      .into();
+  };
+  let members = data.fields.members();
+
+  quote! {
+    impl #impl_generics ::tecs::Bundle for #name #ty_generics #where_clause {
+      fn insert_into(self, world: &mut ::tecs::World, id: ::tecs::EntityId) {
+        #(world.add_component(id, self.#members);)*
+      }
+    }
+  }
+  .into()
+}
+
+/// The `T` of a field declared as `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+  let Type::Path(path) = ty else {
+    return None;
+  };
+  let segment = path.path.segments.last()?;
+  if segment.ident != "Option" {
+    return None;
+  }
+  let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+    return None;
+  };
+  match arguments.args.first()? {
+    GenericArgument::Type(inner) => Some(inner),
+    _ => None,
+  }
+}
+
+#[proc_macro_derive(Components)]
+pub fn derive_components(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  let fields = match &input.data {
+    Data::Struct(data) => match &data.fields {
+      Fields::Named(fields) => &fields.named,
+      _ => {
+        return syn::Error::new_spanned(&input, "`Components` needs named fields")
+          .to_compile_error()
+          .into();
+      }
+    },
+    _ => {
+      return syn::Error::new_spanned(&input, "`Components` can only be derived for structs")
+        .to_compile_error()
+        .into();
+    }
+  };
+  let mut idents = vec![];
+  let mut types = vec![];
+  for field in fields {
+    let Some(inner) = option_inner(&field.ty) else {
+      return syn::Error::new_spanned(&field.ty, "`Components` fields must be `Option`s")
+        .to_compile_error()
+        .into();
+    };
+    idents.push(field.ident.as_ref().expect("named fields have idents"));
+    types.push(inner);
+  }
+  let with = idents.iter().map(|ident| format_ident!("with_{}", ident));
+  let without = idents.iter().map(|ident| format_ident!("without_{}", ident));
+
+  quote! {
+    impl #impl_generics #name #ty_generics #where_clause {
+      pub fn new() -> Self {
+        Self { #(#idents: ::core::option::Option::None,)* }
+      }
+
+      #(
+        pub fn #with(mut self, #idents: #types) -> Self {
+          self.#idents = ::core::option::Option::Some(#idents);
+          self
+        }
+
+        pub fn #without(mut self) -> Self {
+          self.#idents = ::core::option::Option::None;
+          self
+        }
+      )*
+    }
+
+    impl #impl_generics ::tecs::Bundle for #name #ty_generics #where_clause {
+      fn insert_into(self, world: &mut ::tecs::World, id: ::tecs::EntityId) {
+        #(
+          if let ::core::option::Option::Some(component) = self.#idents {
+            world.add_component(id, component);
+          }
+        )*
+      }
+    }
+  }
+  .into()
+}
+
+#[proc_macro_derive(Reflect)]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  let Data::Struct(data) = &input.data else {
+    return syn::Error::new_spanned(&input, "`Reflect` can only be derived for structs")
+      .to_compile_error()
+      .into();
+  };
+  let members: Vec<_> = data.fields.members().collect();
+  let labels: Vec<_> = members
+    .iter()
+    .map(|member| match member {
+      syn::Member::Named(ident) => ident.to_string(),
+      syn::Member::Unnamed(index) => index.index.to_string(),
+    })
+    .collect();
+
+  quote! {
+    impl #impl_generics ::tecs::Reflect for #name #ty_generics #where_clause {
+      fn type_name(&self) -> &'static str {
+        ::core::any::type_name::<Self>()
+      }
+
+      fn as_any(&self) -> &dyn ::core::any::Any {
+        self
+      }
+
+      fn clone_reflect(&self) -> ::tecs::__private::Box<dyn ::tecs::Reflect> {
+        ::tecs::__private::Box::new(Self {
+          #(#members: ::core::clone::Clone::clone(&self.#members),)*
+        })
+      }
+
+      fn fields(&self) -> ::tecs::__private::Vec<(&'static str, &dyn ::core::fmt::Debug)> {
+        ::tecs::__private::vec![#((#labels, &self.#members as &dyn ::core::fmt::Debug)),*]
+      }
+
+      fn field(&self, name: &str) -> ::core::option::Option<&dyn ::core::any::Any> {
+        match name {
+          #(#labels => ::core::option::Option::Some(&self.#members),)*
+          _ => ::core::option::Option::None,
+        }
+      }
+
+      fn field_mut(&mut self, name: &str) -> ::core::option::Option<&mut dyn ::core::any::Any> {
+        match name {
+          #(#labels => ::core::option::Option::Some(&mut self.#members),)*
+          _ => ::core::option::Option::None,
+        }
+      }
+    }
+  }
+  .into()
+}
@@ -0,0 +1,55 @@
+//! Builds `tecs` without its `std` feature, as on a target that only has
+//! `alloc`. Build it on its own so that no other crate turns `std` back on:
+//! `cargo build -p tecs-no-std-check`.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use tecs::{query, query_mut, Bundle, EntityId, Query, Reflect, Res, Schedule, World};
+
+#[derive(Debug, Clone, Reflect)]
+pub struct Position(pub i32, pub i32);
+
+#[derive(Debug, Clone, Reflect)]
+pub struct Velocity(pub i32, pub i32);
+
+pub struct Gravity(pub i32);
+
+#[derive(Bundle)]
+pub struct Body {
+  pub pos: Position,
+  pub vel: Velocity,
+}
+
+fn fall(mut bodies: Query<&mut Velocity>, gravity: Res<Gravity>) {
+  for (_, vel) in bodies.iter() {
+    vel.1 -= gravity.0;
+  }
+}
+
+/// Simulates falling bodies for `steps` fixed steps, despawning those that
+/// fall below the ground, and returns the ids of the bodies left.
+pub fn simulate(steps: u32) -> Vec<EntityId> {
+  let mut world = World::new();
+  world.insert_resource(Gravity(1));
+  for height in 0..4 {
+    world.spawn_bundle(Body { pos: Position(0, height * 10), vel: Velocity(1, 0) });
+  }
+  let mut schedule = Schedule::new();
+  schedule.add_system(fall);
+  for _ in 0..steps {
+    schedule.run_for(&mut world, Duration::from_millis(16));
+    for (_, pos, vel) in query_mut!(world, Position, Velocity) {
+      pos.0 += vel.0;
+      pos.1 += vel.1;
+    }
+    let fallen: Vec<EntityId> = query!(world, Position).filter(|(_, pos)| pos.1 < 0).map(|(id, _)| id).collect();
+    for id in fallen {
+      world.despawn(id);
+    }
+  }
+  world.entities().to_vec()
+}
@@ -0,0 +1,106 @@
+use tecs::{query, Bundle, Components, EntityId, Reflect, ReflectRegistry, World};
+
+#[derive(Debug, PartialEq)]
+struct Position(i32, i32);
+
+#[derive(Debug, PartialEq)]
+struct Velocity(i32, i32);
+
+#[derive(Debug, PartialEq)]
+struct Health(u32);
+
+#[derive(Components)]
+struct Spawn {
+  pos: Option<Position>,
+  vel: Option<Velocity>,
+  health: Option<Health>,
+}
+
+#[derive(Bundle)]
+struct Mover {
+  pos: Position,
+  vel: Velocity,
+}
+
+#[test]
+fn derived_components_spawn_only_the_fields_that_are_set() {
+  let mut world = World::new();
+  let full = world.spawn_bundle(
+    Spawn::new()
+      .with_pos(Position(0, 0))
+      .with_vel(Velocity(1, 0))
+      .with_health(Health(3)),
+  );
+  let still = world.spawn_bundle(Spawn::new().with_pos(Position(5, 5)).with_vel(Velocity(0, 1)).without_vel());
+
+  let moving: Vec<EntityId> = query!(world, Position, Velocity).map(|(id, ..)| id).collect();
+  assert_eq!(moving, vec![full]);
+  let positioned: Vec<EntityId> = query!(world, Position).map(|(id, _)| id).collect();
+  assert_eq!(positioned, vec![full, still]);
+  assert_eq!(world.get::<Health>(full), Some(&Health(3)));
+  assert_eq!(world.get::<Health>(still), None);
+}
+
+#[test]
+fn derived_bundles_insert_every_field() {
+  let mut world = World::new();
+  let id = world.spawn_bundle(Mover { pos: Position(1, 2), vel: Velocity(3, 4) });
+
+  assert_eq!(world.get::<Position>(id), Some(&Position(1, 2)));
+  assert_eq!(world.get::<Velocity>(id), Some(&Velocity(3, 4)));
+}
+
+#[test]
+fn builder_calls_fire_the_add_hook_once() {
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  static ADDED: AtomicUsize = AtomicUsize::new(0);
+  let mut world = World::new();
+  world.on_add::<Position>(|_, _| {
+    ADDED.fetch_add(1, Ordering::SeqCst);
+  });
+  world.spawn_bundle(Spawn::new().with_pos(Position(0, 0)).with_pos(Position(1, 1)).with_health(Health(1)));
+
+  assert_eq!(ADDED.load(Ordering::SeqCst), 1);
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect)]
+struct Stats {
+  name: String,
+  level: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect)]
+struct Point(i32, i32);
+
+#[test]
+fn reflected_fields_are_read_and_written_by_name() {
+  let mut stats = Stats { name: "knight".into(), level: 1 };
+  assert_eq!(stats.field("name").and_then(|name| name.downcast_ref::<String>()), Some(&"knight".into()));
+  *stats.field_mut("level").unwrap().downcast_mut::<u32>().unwrap() += 1;
+  assert_eq!(stats.level, 2);
+  assert!(stats.field("health").is_none());
+  assert!(stats.field_mut("level").unwrap().downcast_mut::<i32>().is_none());
+
+  let mut point = Point(1, 2);
+  *point.field_mut("1").unwrap().downcast_mut::<i32>().unwrap() = 5;
+  assert_eq!(point, Point(1, 5));
+  assert_eq!(point.fields().into_iter().map(|(name, _)| name).collect::<Vec<_>>(), ["0", "1"]);
+  let copy = point.clone_reflect();
+  assert_eq!(copy.as_any().downcast_ref::<Point>(), Some(&point));
+  assert!(copy.type_name().ends_with("Point"));
+}
+
+#[test]
+fn the_registry_formats_reflected_components() {
+  let mut world = World::new();
+  let mut registry = ReflectRegistry::new();
+  registry.register::<Stats>();
+  registry.register::<Point>();
+  world.insert_resource(registry);
+  let id = world.spawn_bundle((Point(1, 2), Stats { name: "knight".into(), level: 3 }, Health(1)));
+
+  let reflected: Vec<String> = world.reflect(id).into_iter().map(|(_, value)| value).collect();
+  assert_eq!(reflected, ["Point(1, 2)", "Stats { name: \"knight\", level: 3 }"]);
+  assert_eq!(World::new().reflect(id), []);
+}
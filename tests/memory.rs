@@ -0,0 +1,56 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tecs::{query, World};
+
+/// Keeps track of the bytes currently allocated. This file holds a single
+/// test so that no other test allocates at the same time.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+    System.alloc(layout)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    System.dealloc(ptr, layout)
+  }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct Position(u64, u64);
+
+struct Name(u64);
+
+const ENTITIES: u64 = 10_000;
+const NAMED: usize = 10;
+
+/// Bytes that naming the last `NAMED` entities of a positioned world takes.
+fn naming_cost() -> usize {
+  let mut world = World::new();
+  let ids = world.spawn_batch((0..ENTITIES).map(|i| (Position(i, i),)));
+  let before = ALLOCATED.load(Ordering::Relaxed);
+  for &id in &ids[ids.len() - NAMED..] {
+    world.add_component(id, Name(id.index() as u64));
+  }
+  let bytes = ALLOCATED.load(Ordering::Relaxed).saturating_sub(before);
+  assert_eq!(query!(world, Position, Name).count(), NAMED);
+  assert_eq!(query!(world, Position).map(|(_, pos)| pos.0 - pos.1).sum::<u64>(), 0);
+  assert!(query!(world, Name).all(|(_, name)| name.0 >= ENTITIES - NAMED as u64));
+  bytes
+}
+
+#[test]
+fn entities_carry_no_slot_for_components_they_lack() {
+  // What an `Option<Name>` field on every entity would take.
+  let slots = ENTITIES as usize * size_of::<Option<Name>>();
+
+  assert!(naming_cost() < slots / 10);
+}